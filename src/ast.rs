@@ -1,38 +1,206 @@
 use crate::token::Token;
 
-
-
 pub type Program = Vec<Statement>;
 
 pub type ExpToken = Token;
 pub type Identifier = String;
 
+/// One statement of a script, as fql::parse gives it. What is in it is free to change, so it is
+/// only there to be run, cloned or compared.
+#[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement{
-    SelectStatement{
+pub enum Statement {
+    SelectStatement {
         counters: Option<ExpToken>,
-        quantity: Option<ExpToken>, //TOP(10) or TAIL(10)
+        quantity: Option<ExpToken>, //TOP(10) or TAIL(10) or LINES(100..200)
+        columns: Vec<Identifier>,   //SELECT name, amount FROM CSV..., the whole row when empty
+        follow: bool,               //TAIL(10) FOLLOW, keep printing new lines as they are appended
         from: ExpToken,
+        record: Option<RecordFormat>, //FROM CSV "data.csv", lines when None
+        source: Source,               //"file.txt", "logs/*.log", DIR "src" or STDIN
+        encoding: Option<Identifier>, //ENCODING "latin1", UTF-8 when there is none
+        condition: Option<Expression>, //where line LIKE '%Wewew%'
+        order: Option<OrderBy>,       //ORDER BY LENGTH DESC, file order when None
+        limit: Option<usize>,         //LIMIT 10, at most this many lines of the result
+        offset: Option<usize>, //OFFSET 20 or SKIP 20, leave out the first lines of the result
+        numbered: bool,        //WITH LINENO, prefix every line with its number in the file
+        output: Option<Format>, //OUTPUT JSON, plain text (or the --format flag) when there is none
+        export: Option<Export>, //> "output.txt" or >> "output.txt"
+    },
+    CreateStatement {
+        target: Identifier,
+        if_not_exists: bool,      //CREATE IF NOT EXISTS "file.txt"
+        content: Vec<Identifier>, //WITH "line1" "line2"
+    },
+    UpdateStatement {
+        target: Identifier,
+        content: Vec<Identifier>,     //SET "line1" "line2"
+        placement: Option<Placement>, //appends to the end of the file when None
+    },
+    ReplaceStatement {
+        target: Identifier,
+        pattern: Identifier,           //REGEX "user=(\w+)"
+        replacement: Identifier,       //"user=$1", capture groups by number or ${name}
+        condition: Option<Expression>, //only touch lines matching the WHERE
+        limit: Option<usize>,          //LIMIT 10, max replacements in the file
+    },
+    DeleteStatement {
+        quantity: Option<ExpToken>, //TOP(2) or TAIL(2) to only drop the first or last matches
         target: Identifier,
-        condition: Option<Expression> //where line LIKE '%Wewew%'
+        condition: Expression,
+    },
+}
+
+impl Statement {
+    /// SELECT ... FOLLOW, which runs until it is stopped
+    pub fn follows(&self) -> bool {
+        matches!(self, Statement::SelectStatement { follow: true, .. })
     }
+
+    /// SELECT ... FROM STDIN
+    pub fn reads_stdin(&self) -> bool {
+        matches!(
+            self,
+            Statement::SelectStatement {
+                source: Source::Stdin,
+                ..
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Path(Identifier), //a single file, or a glob like "logs/*.log" for many
+    Dir(DirSource),
+    Stdin, //STDIN or "-"
+}
+
+//DIR "src" RECURSIVE INCLUDE "*.rs" EXCLUDE "target/**" HIDDEN FOLLOW SYMLINKS
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirSource {
+    pub path: Identifier,
+    pub recursive: bool,
+    pub include: Vec<Identifier>,
+    pub exclude: Vec<Identifier>,
+    pub hidden: bool,
+    pub follow_links: bool,
+}
+
+// how an input is split into records with columns, instead of into lines
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordFormat {
+    Csv {
+        delimiter: char, //DELIMITER ";", a comma for CSV and a tab for TSV
+        header: bool,    //HEADER, the first record names the columns
+    },
+    Json,   //one document, an array of records or a single one
+    Ndjson, //one record per line
+    Regex {
+        pattern: String, //PARSE REGEX "(?P<ip>\S+) ...", a column for every named group
+        unparsed: bool,  //KEEP UNPARSED, lines that do not match are rows with only _unparsed
+    },
+    Preset {
+        name: String, //FORMAT nginx_combined, a regex and the types of its fields, or logfmt
+        unparsed: bool,
+    },
+}
+
+// ORDER BY LENGTH DESC NUMERIC
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub key: SortKey,
+    pub descending: bool, //DESC, ASC is the default
+    pub collation: Collation,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression{
-    Where{
-        conditional: Option<Token>, //conditional one of LIKE or NOTLIKE or REGEX
+pub enum SortKey {
+    Line,
+    Length,             //of the line, in characters
+    Column(Identifier), //a column of FROM CSV, JSON, PARSE..., or a path
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    Default, //numbers (typed ones, and LENGTH) before text, text by code point
+    Numeric, //NUMERIC, the text read as a number, everything that is not one comes last
+    Natural, //NATURAL, runs of digits compare as numbers, "file2" before "file10"
+}
+
+/// OUTPUT JSON, how the results of a SELECT are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// lines as they are, the default
+    Text,
+    /// one array of objects
+    Json,
+    /// one object per line
+    Ndjson,
+    Csv,
+    /// aligned columns with a header
+    Table,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Export {
+    Overwrite(Identifier), //> "output.txt"
+    Append(Identifier),    //>> "output.txt"
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Placement {
+    AtLine(usize),      //AT LINE 3, 1-based
+    Before(Identifier), //BEFORE "pattern", the first line containing the pattern
+    After(Identifier),  //AFTER "pattern"
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Predicate {
+        conditional: Token, //conditional one of LIKE, NOTLIKE, ILIKE, NOTILIKE or REGEX
         target: Identifier,
+        escape: Option<char>, //LIKE "50\%%" ESCAPE "\"
     },
+    And(Box<Expression>, Box<Expression>), //LIKE "ERROR" AND NOTLIKE "healthcheck"
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>), //NOT (LIKE "a" OR LIKE "b")
+    Comparison {
+        left: Operand,
+        op: Op,
+        right: Operand,
+    }, //LINE = "exact", LINE >= 100
+    Between {
+        operand: Operand,
+        low: Operand,
+        high: Operand,
+    }, //LINENO BETWEEN 100 AND 200, inclusive
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Line,               //the whole line
+    LineNo,             //the line's number in the file, starting at 1
+    Column(Identifier), //amount, a column of a record
+    Strings(String),
+    Number(f64),
+    Arithmetic {
+        left: Box<Operand>,
+        op: ExpToken, //one of + - * / %
+        right: Box<Operand>,
+    }, //LINENO % 10
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Op {
-   
     Equals,
     NotEquals,
     Like,
     NotLike,
+    Less,
+    LessEquals,
+    Greater,
+    GreaterEquals,
 }
 
 impl Op {
@@ -40,8 +208,13 @@ impl Op {
         match token {
             Token::Like => Self::Like,
             Token::NotLike => Self::NotLike,
+            Token::Assign => Self::Equals,
             Token::NotEquals => Self::NotEquals,
-            _ => unreachable!("{:?}", token)
+            Token::Less => Self::Less,
+            Token::LessEquals => Self::LessEquals,
+            Token::Export => Self::Greater, //> is a comparison inside WHERE
+            Token::GreaterEquals => Self::GreaterEquals,
+            _ => unreachable!("{:?}", token),
         }
     }
-}
\ No newline at end of file
+}
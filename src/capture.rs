@@ -0,0 +1,150 @@
+use std::{io::BufRead, sync::Arc};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    encoding,
+    interpreter::{InterpreterError, Row},
+    preset::Type,
+    record::Fields,
+};
+
+// the column a line that did not match is kept in, with KEEP UNPARSED
+pub const UNPARSED: &str = "_unparsed";
+
+// FROM "access.log" PARSE REGEX "(?P<ip>\S+) ...": every named group of the regex is a column.
+// A line the regex does not match is dropped, or with KEEP UNPARSED it is a row of its own with
+// every group empty and the whole line in _unparsed, which is empty for the lines that matched.
+pub struct Captures {
+    regex: Regex,
+    names: Arc<[String]>,
+    // of every named group, text unless a FORMAT preset says otherwise
+    types: Vec<Type>,
+    keep_unparsed: bool,
+}
+
+impl Captures {
+    pub fn new(pattern: &str, keep_unparsed: bool) -> Result<Self, InterpreterError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| InterpreterError::InvalidRegex(pattern.to_owned(), e))?;
+        let mut names: Vec<String> = regex.capture_names().flatten().map(str::to_owned).collect();
+        if names.is_empty() {
+            return Err(InterpreterError::NoNamedGroups(pattern.to_owned()));
+        }
+        let types = vec![Type::Text; names.len()];
+        if keep_unparsed {
+            names.push(UNPARSED.to_owned());
+        }
+        Ok(Self {
+            regex,
+            names: names.into(),
+            types,
+            keep_unparsed,
+        })
+    }
+
+    // the groups named in `types` are turned into those types, timestamps or integers
+    pub fn typed(mut self, types: &[(&str, Type)]) -> Self {
+        for (name, kind) in self.names.iter().zip(self.types.iter_mut()) {
+            if let Some((_, given)) = types.iter().find(|(typed, _)| typed == name) {
+                *kind = *given;
+            }
+        }
+        self
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    fn row(&self, number: usize, line: String) -> Option<Row> {
+        let values: Vec<Value> = match self.regex.captures(&line) {
+            Some(captures) => {
+                let mut values: Vec<Value> = self
+                    .regex
+                    .capture_names()
+                    .flatten()
+                    .zip(&self.types)
+                    //a group that took no part in the match is null, not ""
+                    .map(|(name, kind)| {
+                        captures
+                            .name(name)
+                            .map_or(Value::Null, |group| kind.value(group.as_str()))
+                    })
+                    .collect();
+                if self.keep_unparsed {
+                    values.push(Value::Null);
+                }
+                values
+            }
+            None if self.keep_unparsed => {
+                let mut values = vec![Value::Null; self.names.len() - 1];
+                values.push(line.as_str().into());
+                values
+            }
+            None => return None,
+        };
+        Some(Row {
+            number,
+            line,
+            fields: Fields::new(self.names.clone(), values),
+        })
+    }
+
+    // lines are numbered before any of them are dropped, so a row keeps the number of its line
+    pub fn scan_lines<'a>(
+        &'a self,
+        reader: impl BufRead + 'a,
+    ) -> impl Iterator<Item = Result<Row, InterpreterError>> + 'a {
+        reader
+            .lines()
+            .enumerate()
+            .filter_map(move |(i, line)| match line {
+                Ok(line) => self.row(i + 1, line).map(Ok),
+                Err(e) => Some(Err(encoding::read_error(e))),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_turns_named_groups_into_columns() {
+        let input = "10.0.0.1 GET /a\nnot a request\n10.0.0.2 POST\n";
+        let rows = |keep_unparsed: bool| -> Vec<Row> {
+            let captures = Captures::new(
+                r"^(?P<ip>\S+) (?P<method>[A-Z]+)(?: (?P<path>\S+))?$",
+                keep_unparsed,
+            )
+            .unwrap();
+            captures
+                .scan_lines(input.as_bytes())
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        let dropped = rows(false);
+        assert_eq!(
+            dropped.iter().map(|row| row.number).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(dropped[0].fields.get("path"), Some(&json!("/a")));
+        assert_eq!(dropped[1].fields.get("path"), Some(&Value::Null));
+        assert_eq!(dropped[1].fields.get(UNPARSED), None);
+
+        let kept = rows(true);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0].fields.get(UNPARSED), Some(&Value::Null));
+        assert_eq!(kept[1].fields.get("ip"), Some(&Value::Null));
+        assert_eq!(kept[1].fields.get(UNPARSED), Some(&json!("not a request")));
+
+        assert!(matches!(
+            Captures::new(r"(\S+) (\S+)", false),
+            Err(InterpreterError::NoNamedGroups(_))
+        ));
+    }
+}
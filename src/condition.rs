@@ -0,0 +1,395 @@
+use std::{borrow::Cow, cmp::Ordering};
+
+use regex::Regex;
+
+use crate::{
+    ast::{Expression, Op, Operand},
+    interpreter::{InterpreterError, Row},
+    record,
+    token::Token,
+};
+
+// A WHERE expression made ready for matching, so regexes are compiled once per statement and
+// not once per line.
+pub enum Condition {
+    Like(Matcher),
+    NotLike(Matcher),
+    Regex(Regex),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+    Comparison(Operand, Op, Operand),
+    Between(Operand, Operand, Operand),
+}
+
+impl Condition {
+    pub fn compile(expression: &Expression) -> Result<Self, InterpreterError> {
+        match expression {
+            Expression::Predicate {
+                conditional,
+                target,
+                escape,
+            } => match conditional {
+                Token::Like => Ok(Condition::Like(Matcher::compile(target, *escape, false)?)),
+                Token::NotLike => Ok(Condition::NotLike(Matcher::compile(
+                    target, *escape, false,
+                )?)),
+                Token::ILike => Ok(Condition::Like(Matcher::compile(target, *escape, true)?)),
+                Token::NotILike => Ok(Condition::NotLike(Matcher::compile(target, *escape, true)?)),
+                Token::Regex => Regex::new(target)
+                    .map(Condition::Regex)
+                    .map_err(|e| InterpreterError::InvalidRegex(target.clone(), e)),
+                _ => Err(InterpreterError::InvalidQueryToken(
+                    "LIKE, NOTLIKE, ILIKE, NOTILIKE or REGEX after WHERE".to_owned(),
+                )),
+            },
+            Expression::And(left, right) => Ok(Condition::And(
+                Box::new(Condition::compile(left)?),
+                Box::new(Condition::compile(right)?),
+            )),
+            Expression::Or(left, right) => Ok(Condition::Or(
+                Box::new(Condition::compile(left)?),
+                Box::new(Condition::compile(right)?),
+            )),
+            Expression::Not(inner) => Ok(Condition::Not(Box::new(Condition::compile(inner)?))),
+            Expression::Comparison { left, op, right } => match op {
+                Op::Like | Op::NotLike => Err(InterpreterError::InvalidQueryToken(
+                    "=, !=, <, <=, > or >= between two values".to_owned(),
+                )),
+                _ => Ok(Condition::Comparison(
+                    left.clone(),
+                    op.clone(),
+                    right.clone(),
+                )),
+            },
+            Expression::Between { operand, low, high } => Ok(Condition::Between(
+                operand.clone(),
+                low.clone(),
+                high.clone(),
+            )),
+        }
+    }
+
+    // every column the condition looks at, so a missing one is an error and not a silent mismatch
+    pub fn columns(&self) -> Vec<&str> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    fn collect_columns<'a>(&'a self, columns: &mut Vec<&'a str>) {
+        match self {
+            Condition::Like(_) | Condition::NotLike(_) | Condition::Regex(_) => {}
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.collect_columns(columns);
+                right.collect_columns(columns);
+            }
+            Condition::Not(inner) => inner.collect_columns(columns),
+            Condition::Comparison(left, _, right) => {
+                operand_columns(left, columns);
+                operand_columns(right, columns);
+            }
+            Condition::Between(operand, low, high) => {
+                operand_columns(operand, columns);
+                operand_columns(low, columns);
+                operand_columns(high, columns);
+            }
+        }
+    }
+
+    // && and || short-circuit, so the right-hand side is only evaluated when it can change the result
+    pub fn matches(&self, row: &Row) -> bool {
+        match self {
+            Condition::Like(matcher) => matcher.matches(&row.line),
+            Condition::NotLike(matcher) => !matcher.matches(&row.line),
+            Condition::Regex(re) => re.is_match(&row.line),
+            Condition::And(left, right) => left.matches(row) && right.matches(row),
+            Condition::Or(left, right) => left.matches(row) || right.matches(row),
+            Condition::Not(inner) => !inner.matches(row),
+            Condition::Between(operand, low, high) => any(operand, row, |current| {
+                compare(&current, &value(low, row)) != Ordering::Less
+                    && compare(&current, &value(high, row)) != Ordering::Greater
+            }),
+            Condition::Comparison(left, op, right) => any(left, row, |left| {
+                any(right, row, |right| {
+                    let ordering = compare(&left, &right);
+                    match op {
+                        Op::Equals => ordering == Ordering::Equal,
+                        Op::NotEquals => ordering != Ordering::Equal,
+                        Op::Less => ordering == Ordering::Less,
+                        Op::LessEquals => ordering != Ordering::Greater,
+                        Op::Greater => ordering == Ordering::Greater,
+                        Op::GreaterEquals => ordering != Ordering::Less,
+                        Op::Like | Op::NotLike => unreachable!(),
+                    }
+                })
+            }),
+        }
+    }
+}
+
+fn operand_columns<'a>(operand: &'a Operand, columns: &mut Vec<&'a str>) {
+    match operand {
+        Operand::Column(name) => columns.push(name),
+        Operand::Arithmetic { left, right, .. } => {
+            operand_columns(left, columns);
+            operand_columns(right, columns);
+        }
+        _ => {}
+    }
+}
+
+// $.items[*].id = 5 is true when any of the ids is 5, every other operand has a single value
+fn any<'a>(operand: &'a Operand, row: &'a Row, mut matches: impl FnMut(Value<'a>) -> bool) -> bool {
+    match operand {
+        Operand::Column(name) if name.contains("[*]") => match row.fields.get(name) {
+            Some(serde_json::Value::Array(values)) => {
+                values.iter().any(|value| matches(field(value)))
+            }
+            _ => matches(value(operand, row)),
+        },
+        _ => matches(value(operand, row)),
+    }
+}
+
+fn value<'a>(operand: &'a Operand, row: &'a Row) -> Value<'a> {
+    match operand {
+        Operand::Line => Value::Text(Cow::Borrowed(&row.line)),
+        Operand::LineNo => Value::Number(row.number as f64),
+        Operand::Column(name) => row
+            .fields
+            .get(name)
+            .map_or(Value::Text(Cow::Borrowed("")), field),
+        Operand::Strings(s) => Value::Text(Cow::Borrowed(s)),
+        Operand::Number(n) => Value::Number(*n),
+        Operand::Arithmetic { left, op, right } => {
+            //anything that is not a number makes the whole expression NaN, which equals nothing
+            let left = value(left, row).as_number().unwrap_or(f64::NAN);
+            let right = value(right, row).as_number().unwrap_or(f64::NAN);
+            Value::Number(match op {
+                Token::Plus => left + right,
+                Token::Minus => left - right,
+                Token::Star => left * right,
+                Token::Slash => left / right,
+                Token::Percent => left % right,
+                _ => f64::NAN,
+            })
+        }
+    }
+}
+
+// a JSON number stays a number, anything else is compared as its text
+fn field(value: &serde_json::Value) -> Value<'_> {
+    match value.as_f64() {
+        Some(n) => Value::Number(n),
+        None => Value::Text(record::text(value)),
+    }
+}
+
+enum Value<'a> {
+    Text(Cow<'a, str>),
+    Number(f64),
+}
+
+impl Value<'_> {
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            //"inf" and "NaN" parse as f64 but nobody means them as numbers in a text file
+            Value::Text(s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+            Value::Number(n) => Some(*n).filter(|n| n.is_finite()),
+        }
+    }
+}
+
+// Two values compare as numbers when both of them are numbers, "100" and " 42.5 " included, and
+// as plain strings otherwise. So LINE > 9 is true for "10" but LINE > "9" on "apple" is lexical.
+fn compare(left: &Value, right: &Value) -> Ordering {
+    if let (Some(l), Some(r)) = (left.as_number(), right.as_number()) {
+        return l.partial_cmp(&r).unwrap_or(Ordering::Equal);
+    }
+    let text = |value: &Value| match value {
+        Value::Text(s) => s.to_string(),
+        Value::Number(n) => n.to_string(),
+    };
+    text(left).cmp(&text(right))
+}
+
+// A LIKE pattern. Without any `%` or `_` it is a plain substring search, as LIKE always was, and
+// keeps the fast `str::contains` path. With wildcards it has SQL semantics and must match the
+// whole line: `%` is any run of characters, `_` is exactly one character.
+pub enum Matcher {
+    Contains(String),
+    Pattern(Regex),
+}
+
+impl Matcher {
+    pub fn compile(
+        pattern: &str,
+        escape: Option<char>,
+        case_insensitive: bool,
+    ) -> Result<Self, InterpreterError> {
+        let mut literal = String::new();
+        let mut regex = String::new();
+        let mut has_wildcards = false;
+
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                //the escaped character is always literal, a trailing escape stands for itself
+                c if Some(c) == escape => {
+                    let escaped = chars.next().unwrap_or(c);
+                    literal.push(escaped);
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+                '%' => {
+                    has_wildcards = true;
+                    regex.push_str(".*");
+                }
+                '_' => {
+                    has_wildcards = true;
+                    regex.push('.');
+                }
+                c => {
+                    literal.push(c);
+                    regex.push_str(&regex::escape(&c.to_string()));
+                }
+            }
+        }
+
+        if !has_wildcards && !case_insensitive {
+            return Ok(Matcher::Contains(literal));
+        }
+        let regex = if has_wildcards {
+            format!("(?s)^{}$", regex)
+        } else {
+            regex
+        };
+        //(?i) in the regex crate does Unicode simple case folding, not just ASCII
+        let regex = if case_insensitive {
+            format!("(?i){}", regex)
+        } else {
+            regex
+        };
+        Regex::new(&regex)
+            .map(Matcher::Pattern)
+            .map_err(|e| InterpreterError::InvalidRegex(pattern.to_owned(), e))
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        match self {
+            Matcher::Contains(target) => line.contains(target.as_str()),
+            Matcher::Pattern(re) => re.is_match(line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::Statement, parser, token};
+
+    fn row(line: &str) -> Row {
+        Row::new(1, line.to_owned())
+    }
+
+    fn compile(query: &str) -> Condition {
+        match parser::parse(token::generate(query)).unwrap().remove(0) {
+            Statement::SelectStatement {
+                condition: Some(condition),
+                ..
+            } => Condition::compile(&condition).unwrap(),
+            statement => panic!("no WHERE in {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn it_can_combine_conditions() {
+        let condition = compile(
+            r#"SELECT * FROM "a.log" WHERE LIKE "ERROR" AND NOT (LIKE "healthcheck" OR REGEX "^DEBUG");"#,
+        );
+
+        assert!(condition.matches(&row("ERROR disk full")));
+        assert!(!condition.matches(&row("ERROR healthcheck failed")));
+        assert!(!condition.matches(&row("DEBUG ERROR retrying")));
+        assert!(!condition.matches(&row("INFO all good")));
+    }
+
+    #[test]
+    fn it_can_compare_whole_lines_as_numbers_or_strings() {
+        let exact = compile(
+            r#"SELECT * FROM "a.log" WHERE LINE = "exact" OR LINE != "exact" AND LINE >= 10 AND LINE < 100;"#,
+        );
+        assert!(exact.matches(&row("exact")));
+        assert!(!exact.matches(&row("exactly")));
+        assert!(exact.matches(&row(" 42 ")));
+        assert!(!exact.matches(&row("9")));
+        assert!(!exact.matches(&row("100")));
+
+        //"9" > "10" as strings, but 9 < 10 as numbers
+        let lexical = compile(r#"SELECT * FROM "a.log" WHERE LINE > "m";"#);
+        assert!(lexical.matches(&row("zebra")));
+        assert!(!lexical.matches(&row("apple")));
+        let numeric = compile(r#"SELECT * FROM "a.log" WHERE LINE <= "10";"#);
+        assert!(numeric.matches(&row("9")));
+        assert!(!numeric.matches(&row("11.5")));
+    }
+
+    #[test]
+    fn it_can_filter_on_line_numbers() {
+        let condition =
+            compile(r#"SELECT * FROM "a.log" WHERE LINENO BETWEEN 3 AND 5 OR LINENO % 10 = 0;"#);
+        let matching: Vec<usize> = (1..=25)
+            .filter(|number| condition.matches(&Row::new(*number, "any".to_owned())))
+            .collect();
+
+        assert_eq!(matching, vec![3, 4, 5, 10, 20]);
+    }
+
+    #[test]
+    fn it_can_match_like_wildcards_and_escapes() {
+        let starts_with = Matcher::compile("ERROR%", None, false).unwrap();
+        assert!(starts_with.matches("ERROR disk full"));
+        assert!(!starts_with.matches("an ERROR"));
+
+        let one_char = Matcher::compile("v_.0", None, false).unwrap();
+        assert!(one_char.matches("v1.0"));
+        assert!(!one_char.matches("v10.0"));
+
+        let percent = Matcher::compile("%50!%%", Some('!'), false).unwrap();
+        assert!(percent.matches("disk at 50% capacity"));
+        assert!(!percent.matches("disk at 50 capacity"));
+
+        //no wildcards keeps the old substring behaviour
+        assert!(matches!(
+            Matcher::compile("taskid", None, false).unwrap(),
+            Matcher::Contains(_)
+        ));
+        assert!(Matcher::compile("a.b", None, false)
+            .unwrap()
+            .matches("xa.by"));
+        assert!(!Matcher::compile("a.b", None, false)
+            .unwrap()
+            .matches("xaxby"));
+
+        //an `_` is a wildcard now, the README says how to get the old meaning back
+        assert!(!Matcher::compile("user_id", None, false)
+            .unwrap()
+            .matches("user_id=1"));
+        assert!(Matcher::compile("%user!_id%", Some('!'), false)
+            .unwrap()
+            .matches("user_id=1"));
+        assert!(!Matcher::compile("%user!_id%", Some('!'), false)
+            .unwrap()
+            .matches("userXid=1"));
+    }
+
+    #[test]
+    fn it_can_match_ilike_with_unicode_case_folding() {
+        let condition =
+            compile(r#"SELECT * FROM "a.log" WHERE ILIKE "straße" AND NOTILIKE "%Ärger%";"#);
+
+        assert!(condition.matches(&row("Die STRAßE ist lang")));
+        assert!(!condition.matches(&row("die straße, ärger überall")));
+        assert!(Matcher::compile("σ_σ", None, true).unwrap().matches("ΣxΣ"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use tempfile::NamedTempFile;
+
+// The lines of a file that is about to be rewritten in place. Lines are kept as raw bytes so
+// that rewriting never drops or mangles content that is not valid UTF-8, and the original
+// line terminator is remembered so CRLF files stay CRLF.
+pub struct FileLines {
+    pub lines: Vec<Vec<u8>>,
+    newline: &'static [u8],
+    trailing_newline: bool,
+}
+
+impl FileLines {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let newline: &'static [u8] = match bytes.iter().position(|b| *b == b'\n') {
+            Some(i) if i > 0 && bytes[i - 1] == b'\r' => b"\r\n",
+            _ => b"\n",
+        };
+        let trailing_newline = bytes.ends_with(b"\n");
+
+        let mut lines: Vec<Vec<u8>> = bytes
+            .split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line).to_vec())
+            .collect();
+        //split leaves an empty piece after the final terminator...
+        if trailing_newline || bytes.is_empty() {
+            lines.pop();
+        }
+
+        Ok(Self {
+            lines,
+            newline,
+            trailing_newline,
+        })
+    }
+
+    // write to a temp file next to the target and rename it over the original, so a crash
+    // halfway through leaves either the old or the new content but never a mix of both.
+    pub fn write_atomically(&self, path: &Path) -> io::Result<()> {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut temp = NamedTempFile::new_in(dir)?;
+        {
+            let mut writer = io::BufWriter::new(temp.as_file_mut());
+            let last = self.lines.len();
+            for (i, line) in self.lines.iter().enumerate() {
+                writer.write_all(line)?;
+                if i + 1 < last || self.trailing_newline {
+                    writer.write_all(self.newline)?;
+                }
+            }
+            writer.flush()?;
+        }
+        temp.as_file().sync_all()?;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(temp.path(), metadata.permissions())?;
+        }
+        temp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, index: usize, content: &[String]) {
+        let new_lines = content.iter().map(|line| line.as_bytes().to_vec());
+        self.lines.splice(index..index, new_lines);
+        //anything we insert at the very end should still be terminated
+        if index == self.lines.len() - content.len() {
+            self.trailing_newline = true;
+        }
+    }
+
+    pub fn position(&self, pattern: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .position(|line| String::from_utf8_lossy(line).contains(pattern))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_crlf_and_missing_trailing_newline_on_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("windows.txt");
+        fs::write(&path, "one\r\ntwo").unwrap();
+
+        let mut file = FileLines::read(&path).unwrap();
+        assert_eq!(file.lines, vec![b"one".to_vec(), b"two".to_vec()]);
+        file.insert(1, &["inserted".to_owned()]);
+        file.write_atomically(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\r\ninserted\r\ntwo");
+    }
+}
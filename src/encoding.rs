@@ -0,0 +1,264 @@
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+};
+
+use encoding_rs::{CoderResult, DecoderResult, UTF_8};
+
+use crate::interpreter::InterpreterError;
+
+// how many bytes are read from an input at a time before they are decoded
+const CHUNK_SIZE: usize = 8 * 1024;
+
+// How the bytes of an input are turned into text: SELECT ... ENCODING "latin1". Without an
+// ENCODING everything is read as UTF-8, and a byte sequence that is not valid is an error
+// rather than a line quietly going missing. "utf-8-lossy" puts a U+FFFD in its place instead.
+// A byte order mark at the start of an input always wins, so UTF-16 files with a BOM are read
+// correctly without saying so.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encoding {
+    encoding: &'static encoding_rs::Encoding,
+    lossy: bool,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self {
+            encoding: UTF_8,
+            lossy: false,
+        }
+    }
+}
+
+impl Encoding {
+    // any of the WHATWG labels ("latin1", "utf-16le", "shift_jis", ...) plus "utf-8-lossy"
+    pub fn for_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_ascii_lowercase();
+        if label == "utf-8-lossy" || label == "utf8-lossy" {
+            return Some(Self {
+                encoding: UTF_8,
+                lossy: true,
+            });
+        }
+        encoding_rs::Encoding::for_label_no_replacement(label.as_bytes()).map(|encoding| Self {
+            encoding,
+            lossy: false,
+        })
+    }
+
+    // the encoding a file is really in, its byte order mark if it has one and self otherwise
+    pub fn for_file(self, mut file: &File) -> io::Result<Self> {
+        let mut bom = [0; 3];
+        file.seek(SeekFrom::Start(0))?;
+        let mut len = 0;
+        while len < bom.len() {
+            match file.read(&mut bom[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        Ok(match encoding_rs::Encoding::for_bom(&bom[..len]) {
+            Some((encoding, _)) => Self { encoding, ..self },
+            None => self,
+        })
+    }
+
+    // whether a newline is always a single b'\n' byte, so lines can be found without decoding
+    pub fn is_ascii_compatible(&self) -> bool {
+        self.encoding.is_ascii_compatible()
+    }
+
+    // a decoder for an input from its first byte on, a byte order mark is used and left out
+    pub fn decoder(&self, input: String) -> Decoder {
+        Decoder {
+            inner: self.encoding.new_decoder(),
+            encoding: *self,
+            input,
+            offset: 0,
+        }
+    }
+
+    // a decoder for an input from `offset` on, where there can be no byte order mark anymore
+    pub fn decoder_at(&self, input: String, offset: u64) -> Decoder {
+        Decoder {
+            inner: self.encoding.new_decoder_without_bom_handling(),
+            encoding: *self,
+            input,
+            offset,
+        }
+    }
+
+    // `reader` as UTF-8, which is what everything after reading works with
+    pub fn decode<R: Read>(&self, reader: R, input: String) -> Decoded<R> {
+        Decoded {
+            reader,
+            decoder: self.decoder(input),
+            chunk: vec![0; CHUNK_SIZE],
+            text: String::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+}
+
+// Decodes one input chunk by chunk, and knows where in the input it is so a byte sequence that
+// is not valid can be pointed at.
+pub struct Decoder {
+    inner: encoding_rs::Decoder,
+    encoding: Encoding,
+    input: String,
+    offset: u64,
+}
+
+impl Decoder {
+    // decode `bytes` onto the end of `text`, `last` once the input has ended
+    pub fn decode(
+        &mut self,
+        mut bytes: &[u8],
+        last: bool,
+        text: &mut String,
+    ) -> Result<(), InterpreterError> {
+        loop {
+            if self.encoding.lossy {
+                text.reserve(
+                    self.inner
+                        .max_utf8_buffer_length(bytes.len())
+                        .unwrap_or(bytes.len()),
+                );
+                let (result, read, _) = self.inner.decode_to_string(bytes, text, last);
+                self.offset += read as u64;
+                bytes = &bytes[read..];
+                if result == CoderResult::InputEmpty {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            text.reserve(
+                self.inner
+                    .max_utf8_buffer_length_without_replacement(bytes.len())
+                    .unwrap_or(bytes.len()),
+            );
+            let (result, read) = self
+                .inner
+                .decode_to_string_without_replacement(bytes, text, last);
+            match result {
+                DecoderResult::InputEmpty => {
+                    self.offset += read as u64;
+                    return Ok(());
+                }
+                DecoderResult::OutputFull => {
+                    self.offset += read as u64;
+                    bytes = &bytes[read..];
+                }
+                //`read` goes past the bad sequence by `extra` bytes
+                DecoderResult::Malformed(bad, extra) => {
+                    let at = self.offset + read as u64 - extra as u64 - bad as u64;
+                    return Err(InterpreterError::InvalidEncoding(
+                        self.input.clone(),
+                        self.encoding.encoding.name().to_owned(),
+                        at,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// A reader over any input that hands out UTF-8.
+pub struct Decoded<R> {
+    reader: R,
+    decoder: Decoder,
+    chunk: Vec<u8>,
+    text: String,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> Read for Decoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.text.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.text.clear();
+            self.position = 0;
+            let read = self.reader.read(&mut self.chunk)?;
+            self.finished = read == 0;
+            self.decoder
+                .decode(&self.chunk[..read], self.finished, &mut self.text)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        }
+
+        let n = buf.len().min(self.text.len() - self.position);
+        buf[..n].copy_from_slice(&self.text.as_bytes()[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+// Reading can only fail with an io::Error, so a decoding error travels inside one. This gets it
+// back out.
+pub fn read_error(e: io::Error) -> InterpreterError {
+    match e.get_ref().map(|inner| inner.is::<InterpreterError>()) {
+        Some(true) => *e
+            .into_inner()
+            .unwrap()
+            .downcast::<InterpreterError>()
+            .unwrap(),
+        _ => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+
+    use super::*;
+
+    fn lines(label: &str, bytes: &[u8]) -> Result<Vec<String>, InterpreterError> {
+        let encoding = Encoding::for_label(label).unwrap();
+        io::BufReader::new(encoding.decode(bytes, "test.log".to_owned()))
+            .lines()
+            .map(|line| line.map_err(read_error))
+            .collect()
+    }
+
+    #[test]
+    fn it_can_decode_other_encodings_and_byte_order_marks() {
+        assert_eq!(
+            lines("latin1", b"caf\xe9\nna\xefve\n").unwrap(),
+            vec!["café", "naïve"]
+        );
+        assert_eq!(
+            lines("utf-16le", b"h\0i\0\n\0\xe9\0\n\0").unwrap(),
+            vec!["hi", "é"]
+        );
+        assert_eq!(
+            lines("utf-8", b"\xef\xbb\xbfbom\nline\n").unwrap(),
+            vec!["bom", "line"]
+        );
+        //a BOM is trusted over the encoding that was asked for
+        assert_eq!(lines("utf-8", b"\xff\xfeo\0k\0").unwrap(), vec!["ok"]);
+        assert_eq!(
+            lines("utf-8-lossy", b"ok\nbad \xff\n").unwrap(),
+            vec!["ok", "bad \u{fffd}"]
+        );
+        assert!(Encoding::for_label("klingon").is_none());
+    }
+
+    #[test]
+    fn it_reports_where_the_first_invalid_sequence_is() {
+        let mut bytes = "line\n".repeat(3000).into_bytes();
+        bytes.extend_from_slice(b"bad \xc3\x28\n");
+        match lines("utf-8", &bytes) {
+            Err(InterpreterError::InvalidEncoding(input, encoding, offset)) => {
+                assert_eq!(
+                    (input.as_str(), encoding.as_str(), offset),
+                    ("test.log", "UTF-8", 15004)
+                );
+            }
+            other => panic!("expected an encoding error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs::{self, File, Metadata},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    condition::Condition,
+    encoding::Decoder,
+    interpreter::{InterpreterError, Row},
+    pipeline::Pipeline,
+};
+
+// how often a followed file is checked for new lines
+#[cfg(not(test))]
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// tests wait for the rows they expect rather than for a while, so they can poll much faster
+#[cfg(test)]
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// SELECT TAIL(n) FOLLOW: print the last n matching lines like any TAIL, then keep watching the
+// file and print every new matching line as it is appended, like `tail -F | grep`, until `stop`
+// is set.
+//
+// The file is polled rather than watched, which works the same on every platform and file system
+// (network shares included). Both ways logs are usually rotated are handled: a file truncated in
+// place is read again from the start, and when the file is renamed away and a new one is created
+// in its place, whatever was still written to the old file is read first and then the new one is
+// followed from its first line.
+pub fn follow<F>(
+    pipeline: &Pipeline,
+    path: &Path,
+    stop: &AtomicBool,
+    mut sink: F,
+) -> Result<(), InterpreterError>
+where
+    F: FnMut(Row) -> Result<(), InterpreterError>,
+{
+    let name = path.display().to_string();
+    let file = open(path)?;
+    let len = file.metadata()?.len();
+    pipeline.run_file(&file, len, &name, &mut pipeline.page(), &mut sink)?;
+
+    let encoding = pipeline.encoding.for_file(&file)?;
+    //counting lines is a whole extra pass over the file, so only when WITH LINENO needs it
+    let lines = if pipeline.numbered {
+        (&file).seek(SeekFrom::Start(0))?;
+        BufReader::new(encoding.decode((&file).take(len), name.clone()))
+            .split(b'\n')
+            .count()
+    } else {
+        0
+    };
+    let mut followed = Followed {
+        file,
+        position: len,
+        lines,
+        decoder: encoding.decoder_at(name.clone(), len),
+        partial: String::new(),
+    };
+
+    while !stop.load(Ordering::SeqCst) {
+        followed.read_new(pipeline.condition, &mut sink)?;
+
+        let rotated = match fs::metadata(path) {
+            Ok(current) => {
+                !is_same_file(&followed.file.metadata()?, &current)
+                    || current.len() < followed.position
+            }
+            //renamed away and not created again yet, keep reading the old one until it is
+            Err(_) => false,
+        };
+        if rotated {
+            if let Ok(file) = open(path) {
+                //whatever was written to the old file before the new one turned up comes first
+                followed.read_new(pipeline.condition, &mut sink)?;
+                followed = Followed {
+                    decoder: pipeline.encoding.for_file(&file)?.decoder(name.clone()),
+                    file,
+                    position: 0,
+                    lines: 0,
+                    partial: String::new(),
+                };
+                followed.read_new(pipeline.condition, &mut sink)?;
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+// the file being followed and how far into it we are
+struct Followed {
+    file: File,
+    position: u64,
+    lines: usize,
+    // keeps a character that is split over two writes together
+    decoder: Decoder,
+    // the start of a line whose newline has not been written yet
+    partial: String,
+}
+
+impl Followed {
+    fn read_new<F>(
+        &mut self,
+        condition: Option<&Condition>,
+        sink: &mut F,
+    ) -> Result<(), InterpreterError>
+    where
+        F: FnMut(Row) -> Result<(), InterpreterError>,
+    {
+        self.file.seek(SeekFrom::Start(self.position))?;
+        let mut chunk = [0; 8 * 1024];
+        loop {
+            let read = self.file.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            self.position += read as u64;
+            self.decoder
+                .decode(&chunk[..read], false, &mut self.partial)?;
+        }
+
+        let mut start = 0;
+        while let Some(end) = self.partial[start..].find('\n') {
+            let line = &self.partial[start..start + end];
+            start += end + 1;
+            self.lines += 1;
+            let row = Row::new(
+                self.lines,
+                line.strip_suffix('\r').unwrap_or(line).to_owned(),
+            );
+            if condition.is_none_or(|condition| condition.matches(&row)) {
+                sink(row)?;
+            }
+        }
+        self.partial.drain(..start);
+        Ok(())
+    }
+}
+
+fn open(path: &Path) -> Result<File, InterpreterError> {
+    File::open(path)
+        .map_err(|_| InterpreterError::InvalidOrUnavailableFile(path.display().to_string()))
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+// without inode numbers a replaced file can only be told apart when it is shorter than the old
+// one, which is the common case for a fresh log anyway
+#[cfg(not(unix))]
+fn is_same_file(_: &Metadata, _: &Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Mutex};
+
+    use super::*;
+    use crate::{ast::Expression, encoding::Encoding, pipeline::Quantity, token::Token};
+
+    fn append(path: &Path, content: &str) {
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    fn wait_for(rows: &Mutex<Vec<String>>, count: usize) {
+        for _ in 0..2000 {
+            if rows.lock().unwrap().len() >= count {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        panic!("expected {} rows, got {:?}", count, rows.lock().unwrap());
+    }
+
+    #[test]
+    fn it_can_follow_a_file_through_appends_truncation_and_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "ERROR 1\nok\nERROR 2\nok\n").unwrap();
+
+        let condition = Condition::compile(&Expression::Predicate {
+            conditional: Token::Like,
+            target: "ERROR".to_owned(),
+            escape: None,
+        })
+        .unwrap();
+        let pipeline = Pipeline {
+            condition: Some(&condition),
+            quantity: Quantity::Tail(1),
+            offset: 0,
+            limit: None,
+            numbered: true,
+            encoding: Encoding::default(),
+            records: None,
+            order: None,
+        };
+        let stop = AtomicBool::new(false);
+        let rows = Mutex::new(Vec::new());
+
+        thread::scope(|scope| {
+            let following = scope.spawn(|| {
+                follow(&pipeline, &path, &stop, |row| {
+                    rows.lock()
+                        .unwrap()
+                        .push(format!("{}:{}", row.number, row.line));
+                    Ok(())
+                })
+            });
+
+            wait_for(&rows, 1);
+            append(&path, "ERROR 3\nok\nERR");
+            wait_for(&rows, 2);
+            append(&path, "OR 4\n");
+            wait_for(&rows, 3);
+            //copytruncate
+            fs::write(&path, "ERROR 5\n").unwrap();
+            wait_for(&rows, 4);
+            //rename and create, with a last line written to the old file after the rename
+            fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+            append(&dir.path().join("app.log.1"), "ERROR 6\n");
+            append(&path, "ok\nERROR 7\n");
+            wait_for(&rows, 6);
+
+            stop.store(true, Ordering::SeqCst);
+            following.join().unwrap().unwrap();
+        });
+
+        assert_eq!(
+            rows.into_inner().unwrap(),
+            vec![
+                "3:ERROR 2",
+                "5:ERROR 3",
+                "7:ERROR 4",
+                "1:ERROR 5",
+                "2:ERROR 6",
+                "2:ERROR 7"
+            ]
+        );
+    }
+}
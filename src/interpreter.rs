@@ -1,193 +1,565 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader},
+    fs::{self, OpenOptions},
+    io::{BufWriter, ErrorKind, Write},
     path::Path,
+    sync::atomic::AtomicBool,
 };
 
 use thiserror::Error;
 
-use crate::{ast::*, token::Token};
-use regex::Regex;
+use crate::{
+    ast::*,
+    condition::Condition,
+    edit::FileLines,
+    encoding::Encoding,
+    follow,
+    output::Printer,
+    pipeline::{Counter, Pipeline, Quantity},
+    record::{Fields, Records},
+    result::{Collector, Header, QueryResult, Results, Sink},
+    source::{self, is_same_file, Input},
+    token::Token,
+};
+use regex::{bytes, Regex};
 
 const DEFAULT_N_COUNT: usize = 10;
-pub fn interprete(program: Program) -> Result<(), Box<dyn std::error::Error>> {
-    let mut ast = program.iter();
 
-    while let Some(statement) = ast.next() {
-        execute_statement(statement)?;
+/// a line together with its 1-based number in the file it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub number: usize,
+    pub line: String,
+    /// the columns of a record (FROM CSV...), none for a plain line
+    pub fields: Fields,
+}
+
+impl Row {
+    pub fn new(number: usize, line: String) -> Self {
+        Self {
+            number,
+            line,
+            fields: Fields::default(),
+        }
+    }
+}
+
+// What a Session can change about how statements run.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Options<'a> {
+    // a statement that produces more rows than this fails once it gets there
+    pub max_rows: Option<usize>,
+    // a TAIL FOLLOW ends once this is set, without it a FOLLOW never ends by itself
+    pub stop: Option<&'a AtomicBool>,
+}
+
+/// Run every statement and hand its results to `sink` as they come, which is how the command line
+/// prints them while a big file is still being read.
+pub fn execute(program: &[Statement], sink: &mut dyn Sink) -> Result<(), InterpreterError> {
+    execute_with(program, sink, Options::default())
+}
+
+pub fn execute_with(
+    program: &[Statement],
+    sink: &mut dyn Sink,
+    options: Options,
+) -> Result<(), InterpreterError> {
+    for statement in program.iter() {
+        execute_statement(statement, sink, options)?;
     }
 
     Ok(())
 }
 
-fn execute_statement(statement: &Statement) -> Result<(), InterpreterError> {
-    let mut query = String::new();
+/// Run every statement and keep the results, one QueryResult per statement.
+pub fn interprete(program: &[Statement]) -> Result<Vec<QueryResult>, InterpreterError> {
+    let mut collector = Collector::default();
+    execute(program, &mut collector)?;
+    Ok(collector.results)
+}
 
+fn execute_statement(
+    statement: &Statement,
+    sink: &mut dyn Sink,
+    options: Options,
+) -> Result<(), InterpreterError> {
     match statement {
         Statement::SelectStatement {
             counters,
             quantity,
-            from,
-            target,
+            columns,
+            follow,
+            from: _,
+            record,
+            source,
+            encoding,
             condition,
+            order,
+            limit,
+            offset,
+            numbered,
+            output,
+            export,
         } => {
-            let mut path = Path::new("");
-            println!("Matched SelectStatement variant:");
-            if let Some(qty) = quantity {
-                println!("Quantity: {:?}", qty);
-            } else {
-                println!("Quantity: None");
-            }
-            println!("From: {:?}", from);
-            println!("Target: {:?}", target);
-            if target.is_empty() {
-                return Err(InterpreterError::InvalidOrUnavailableFile(target.clone()));
-            }
-            path = Path::new(target);
-            if let Ok(file) = File::open(path) {
-                let reader = BufReader::new(file);
-                let mut lines: Vec<String> = reader.lines().filter_map(|line| line.ok()).collect();
-
-                //DO WHERE conditionals...here
-                if let Some(cond) = condition {
-                    println!("Condition: {:?}", cond);
-                    // let lines_containing_phrase: Vec<String> = my_vec
-                    //     .iter()
-                    //     .filter(|line| line.contains(phrase_to_find))
-                    //     .cloned()
-                    //     .collect();
-                    match cond {
-                        Expression::Where {
-                            conditional,
-                            target,
-                        } => match conditional {
-                            Some(Token::Like) => {
-                                lines = lines
-                                    .iter()
-                                    .filter(|line| line.contains(target))
-                                    .cloned()
-                                    .collect();
-                            }
-                            Some(Token::NotLike) => {
-                                lines = lines
-                                    .iter()
-                                    .filter(|line| !line.contains(target))
-                                    .cloned()
-                                    .collect();
-                            }
-                            Some(Token::Regex) => {
-                                let re = Regex::new(&target).unwrap();
-                                // Find all matches in the text
-                                lines = lines
-                                    .iter()
-                                    //.filter(|line| !line.contains(target))
-                                    .filter(|line| re.is_match(line) )
-                                    .cloned()
-                                    .collect();
-                            }
-                            _ => unreachable!(),
-                        },
-
-                        _ => {
-                            return Err(InterpreterError::InvalidQueryToken(
-                                "* or TOP or TAIL token after SELECT".to_owned(),
-                            ))
-                        }
-                    }
-                } else {
-                    println!("Condition: None");
+            if let Source::Path(target) = source {
+                if target.is_empty() {
+                    return Err(InterpreterError::InvalidOrUnavailableFile(target.clone()));
                 }
-
-                
-
-                if let Some(qty) = quantity {
-                    match qty {
-                        Token::Star => {
-                            if let Some(counter) = counters {
-                                run_counter(counter, &lines);
-                            } else {
-                                //how about we vec.join? into some output variable...
-                                for line in lines {
-                                    println!("{}", line);
-                                }
-                            }
-                        }
-                        Token::Top(s) => {
-                            //println!("Top({})", s);
-                            let n = get_n_from_token(s);
-                            let top_n_lines = &lines[..n];
-                            if let Some(counter) = counters {
-                                run_counter(counter, &top_n_lines);
-                            } else {
-                                for line in top_n_lines.iter() {
-                                    //println!("{}: {}", i + 1, line);
-                                    println!("{}", line);
-                                }
-                            }
-                        }
-                        Token::Tail(s) => {
-                            //println!("Tail({})", s);
-                            let n = get_n_from_token(s);
-                            let total_lines = lines.len();
-                            let bottom_n_lines = &lines[total_lines - n..];
-
-                            if let Some(counter) = counters {
-                                run_counter(counter, &bottom_n_lines);
-                            } else {
-                                for line in bottom_n_lines.iter() {
-                                    //println!("{}: {}", i + 1, line);
-                                    println!("{}", line);
-                                }
-                            }
-                        }
-
-                        _ => unreachable!(),
-                    }
-                } else {
-                    println!("Invalid query token... ");
+            }
+            let qty = match quantity {
+                Some(qty) => qty,
+                None => {
                     return Err(InterpreterError::InvalidQueryToken(
                         "* or TOP or TAIL token after SELECT".to_owned(),
                     ));
                 }
+            };
+
+            //a glob like "logs/*.log" or a DIR expands to many files, and then every result says which file it is from
+            let (sources, prefixed) = source::expand(source, export.as_ref())?;
+            // TAIL FOLLOW never ends by itself, so it watches exactly one file
+            let followed = match sources.as_slice() {
+                _ if !*follow => None,
+                [Input::File(path)] if !prefixed => Some(path),
+                _ => {
+                    return Err(InterpreterError::CannotFollow(match source {
+                        Source::Stdin => "STDIN".to_owned(),
+                        Source::Path(target) => target.clone(),
+                        Source::Dir(dir) => dir.path.clone(),
+                    }))
+                }
+            };
+            let condition = condition.as_ref().map(Condition::compile).transpose()?;
+            //the columns WHERE and ORDER BY look at, besides the ones the SELECT keeps
+            let mut referenced = condition
+                .as_ref()
+                .map(Condition::columns)
+                .unwrap_or_default();
+            if let Some(OrderBy {
+                key: SortKey::Column(column),
+                ..
+            }) = order
+            {
+                referenced.push(column);
+            }
+            let records = match record {
+                Some(format) => Some(Records::new(format.clone(), columns, &referenced)?),
+                None => {
+                    //a plain line has no columns to select or compare
+                    if let Some(column) =
+                        columns.iter().map(String::as_str).chain(referenced).next()
+                    {
+                        return Err(InterpreterError::NoColumns(column.to_owned()));
+                    }
+                    None
+                }
+            };
+            //an export gets a Printer of its own, and the caller only hears that the statement is done
+            let mut exported = match export {
+                Some(export) => Some(Printer::new(open_export(export, &sources)?)),
+                None => None,
+            };
+            let out = &mut Limited {
+                sink: match exported.as_mut() {
+                    Some(printer) => printer,
+                    None => &mut *sink,
+                },
+                max_rows: options.max_rows,
+                rows: 0,
+            };
+            let header = Header {
+                results: Results::for_counter(counters.as_ref()),
+                format: output.unwrap_or(Format::Text),
+                prefixed,
+                numbered: *numbered,
+            };
+
+            let pipeline = Pipeline {
+                condition: condition.as_ref(),
+                quantity: get_quantity_from_token(qty),
+                offset: offset.unwrap_or(0),
+                limit: *limit,
+                numbered: out.needs_line_numbers(&header),
+                encoding: match encoding {
+                    Some(label) => Encoding::for_label(label)
+                        .ok_or_else(|| InterpreterError::UnknownEncoding(label.clone()))?,
+                    None => Encoding::default(),
+                },
+                records: records.as_ref(),
+                order: order.as_ref(),
+            };
+
+            //the sink only hears of the statement once every check above has passed
+            out.begin(&header)?;
+
+            if let Some(path) = followed {
+                let file = path.display().to_string();
+                let never = AtomicBool::new(false);
+                follow::follow(&pipeline, path, options.stop.unwrap_or(&never), |row| {
+                    out.row(&file, row)?;
+                    //every line shows up (or lands in the export) as soon as it is seen
+                    out.flush()
+                })?;
             } else {
-                return Err(InterpreterError::InvalidOrUnavailableFile(target.clone()));
+                run_select(&pipeline, counters.as_ref(), &sources, prefixed, out)?;
             }
+            //each statement's results are complete on disk before the next statement runs
+            out.end()?;
 
-            //do the actual running here...
+            if exported.is_some() {
+                sink.begin(&Header::done())?;
+                sink.end()?;
+            }
             Ok(())
         }
-        _ => todo!(),
+
+        Statement::CreateStatement {
+            target,
+            if_not_exists,
+            content,
+        } => {
+            create_file(target, *if_not_exists, content)?;
+            done(sink)
+        }
+        Statement::UpdateStatement {
+            target,
+            content,
+            placement,
+        } => {
+            update_file(target, content, placement.as_ref())?;
+            done(sink)
+        }
+        Statement::ReplaceStatement {
+            target,
+            pattern,
+            replacement,
+            condition,
+            limit,
+        } => {
+            let replaced =
+                replace_in_file(target, pattern, replacement, condition.as_ref(), *limit)?;
+            edited(sink, "replaced", target, replaced)
+        }
+        Statement::DeleteStatement {
+            quantity,
+            target,
+            condition,
+        } => {
+            let removed = delete_from_file(target, quantity.as_ref(), condition)?;
+            edited(sink, "deleted", target, removed)
+        }
     }
 }
 
-fn run_counter(counter: &Token, lines: &[String]) {
-    match counter {
-        Token::CountC => {
-            let total_chars = lines.iter().map(|s| s.chars().count()).sum::<usize>();
+// every line of every source through the pipeline, as rows or as a count per source
+fn run_select(
+    pipeline: &Pipeline,
+    counter: Option<&Token>,
+    sources: &[Input],
+    prefixed: bool,
+    sink: &mut dyn Sink,
+) -> Result<(), InterpreterError> {
+    let names: Vec<String> = sources.iter().map(Input::to_string).collect();
+    let Some(counter) = counter else {
+        //DO WHERE conditionals while reading, every line goes straight from the file to the sink
+        return pipeline.run_inputs(sources, |i, row| sink.row(&names[i], row));
+    };
 
-            println!("{}", total_chars);
+    let mut counters: Vec<Counter> = sources.iter().map(|_| Counter::new(counter)).collect();
+    pipeline.run_inputs(sources, |i, row| {
+        counters[i].add(&row);
+        Ok(())
+    })?;
+    for (name, counter) in names.iter().zip(&counters) {
+        sink.count(Some(name), counter.count())?;
+    }
+    if prefixed {
+        sink.count(None, counters.iter().map(Counter::count).sum())?;
+    }
+    Ok(())
+}
+
+// counts the rows of a statement on their way to its sink, the caller's or an export
+struct Limited<'a> {
+    sink: &'a mut dyn Sink,
+    max_rows: Option<usize>,
+    rows: usize,
+}
+
+impl Sink for Limited<'_> {
+    fn begin(&mut self, header: &Header) -> Result<(), InterpreterError> {
+        self.sink.begin(header)
+    }
+
+    fn row(&mut self, file: &str, row: Row) -> Result<(), InterpreterError> {
+        self.rows += 1;
+        match self.max_rows {
+            Some(max_rows) if self.rows > max_rows => Err(InterpreterError::TooManyRows(max_rows)),
+            _ => self.sink.row(file, row),
         }
-        Token::CountL => {
-            println!("{}", lines.len());
+    }
+
+    fn count(&mut self, file: Option<&str>, count: usize) -> Result<(), InterpreterError> {
+        self.sink.count(file, count)
+    }
+
+    fn end(&mut self) -> Result<(), InterpreterError> {
+        self.sink.end()
+    }
+
+    fn flush(&mut self) -> Result<(), InterpreterError> {
+        self.sink.flush()
+    }
+
+    fn needs_line_numbers(&self, header: &Header) -> bool {
+        self.sink.needs_line_numbers(header)
+    }
+}
+
+// a statement that only changes a file has nothing to show
+fn done(sink: &mut dyn Sink) -> Result<(), InterpreterError> {
+    sink.begin(&Header::done())?;
+    sink.end()
+}
+
+// REPLACE and DELETE show how many lines they changed, as "target:count"
+fn edited(
+    sink: &mut dyn Sink,
+    name: &'static str,
+    target: &str,
+    count: usize,
+) -> Result<(), InterpreterError> {
+    sink.begin(&Header {
+        results: Results::Counts(name),
+        format: Format::Text,
+        prefixed: true,
+        numbered: false,
+    })?;
+    sink.count(Some(target), count)?;
+    sink.end()
+}
+
+fn delete_from_file(
+    target: &str,
+    quantity: Option<&Token>,
+    condition: &Expression,
+) -> Result<usize, InterpreterError> {
+    let path = Path::new(target);
+    if target.is_empty() || !path.is_file() {
+        return Err(InterpreterError::InvalidOrUnavailableFile(
+            target.to_owned(),
+        ));
+    }
+    let condition = Condition::compile(condition)?;
+    no_columns(&condition)?;
+    let mut file = FileLines::read(path)?;
+
+    let mut matched: Vec<usize> = file
+        .lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| {
+            condition.matches(&Row::new(i + 1, String::from_utf8_lossy(line).into_owned()))
+        })
+        .map(|(i, _)| i)
+        .collect();
+    match quantity {
+        Some(Token::Top(s)) => matched.truncate(get_n_from_token(s)),
+        Some(Token::Tail(s)) => {
+            let keep_from = matched.len().saturating_sub(get_n_from_token(s));
+            matched.drain(..keep_from);
         }
-        Token::CountW => {
-            let total_words = lines
-                .iter()
-                .map(|s| s.split_whitespace().count())
-                .sum::<usize>();
+        _ => {}
+    }
+
+    if matched.is_empty() {
+        return Ok(0);
+    }
+    let mut remove = vec![false; file.lines.len()];
+    for i in &matched {
+        remove[*i] = true;
+    }
+    let mut remove = remove.into_iter();
+    file.lines.retain(|_| !remove.next().unwrap_or(false));
+    file.write_atomically(path)
+        .map_err(|e| InterpreterError::CannotWriteFile(target.to_owned(), e))?;
+
+    Ok(matched.len())
+}
+
+fn replace_in_file(
+    target: &str,
+    pattern: &str,
+    replacement: &str,
+    condition: Option<&Expression>,
+    limit: Option<usize>,
+) -> Result<usize, InterpreterError> {
+    let path = Path::new(target);
+    if target.is_empty() || !path.is_file() {
+        return Err(InterpreterError::InvalidOrUnavailableFile(
+            target.to_owned(),
+        ));
+    }
+    //bytes::Regex so lines that are not valid UTF-8 are still rewritten untouched...
+    let re = bytes::Regex::new(pattern)
+        .map_err(|e| InterpreterError::InvalidRegex(pattern.to_owned(), e))?;
+    let condition = condition.map(Condition::compile).transpose()?;
+    if let Some(condition) = &condition {
+        no_columns(condition)?;
+    }
+    let mut file = FileLines::read(path)?;
 
-            println!("{}", total_words);
+    let mut replaced = 0;
+    for (i, line) in file.lines.iter_mut().enumerate() {
+        let remaining = limit.map_or(usize::MAX, |limit| limit - replaced);
+        if remaining == 0 {
+            break;
+        }
+        if let Some(condition) = &condition {
+            let row = Row::new(i + 1, String::from_utf8_lossy(line).into_owned());
+            if !condition.matches(&row) {
+                continue;
+            }
+        }
+        let found = re.find_iter(line).count().min(remaining);
+        if found == 0 {
+            continue;
         }
+        *line = re
+            .replacen(line, found, replacement.as_bytes())
+            .into_owned();
+        replaced += found;
+    }
 
-        _ => unreachable!(),
+    if replaced > 0 {
+        file.write_atomically(path)
+            .map_err(|e| InterpreterError::CannotWriteFile(target.to_owned(), e))?;
     }
+    Ok(replaced)
+}
+
+// DELETE and REPLACE work on plain lines, so a column in their WHERE is a typo that would match
+// every line (or none) instead of failing
+fn no_columns(condition: &Condition) -> Result<(), InterpreterError> {
+    match condition.columns().first() {
+        Some(column) => Err(InterpreterError::NoColumns(column.to_string())),
+        None => Ok(()),
+    }
+}
+
+fn update_file(
+    target: &str,
+    content: &[String],
+    placement: Option<&Placement>,
+) -> Result<(), InterpreterError> {
+    let path = Path::new(target);
+    if target.is_empty() || !path.is_file() {
+        return Err(InterpreterError::InvalidOrUnavailableFile(
+            target.to_owned(),
+        ));
+    }
+    let mut file = FileLines::read(path)?;
+
+    let index = match placement {
+        None => file.lines.len(),
+        Some(Placement::AtLine(n)) => {
+            //AT LINE (last + 1) is the same as appending, and lines count from 1
+            if *n == 0 || *n > file.lines.len() + 1 {
+                return Err(InterpreterError::LineOutOfRange(*n, target.to_owned()));
+            }
+            n - 1
+        }
+        Some(Placement::Before(pattern)) => file
+            .position(pattern)
+            .ok_or_else(|| InterpreterError::PatternNotFound(pattern.clone(), target.to_owned()))?,
+        Some(Placement::After(pattern)) => {
+            file.position(pattern).ok_or_else(|| {
+                InterpreterError::PatternNotFound(pattern.clone(), target.to_owned())
+            })? + 1
+        }
+    };
+    file.insert(index, content);
+
+    file.write_atomically(path)
+        .map_err(|e| InterpreterError::CannotWriteFile(target.to_owned(), e))
 }
 
-fn get_n_from_token(token: &String) -> usize {
+fn create_file(
+    target: &str,
+    if_not_exists: bool,
+    content: &[String],
+) -> Result<(), InterpreterError> {
+    if target.is_empty() {
+        return Err(InterpreterError::InvalidOrUnavailableFile(
+            target.to_owned(),
+        ));
+    }
+    let path = Path::new(target);
+    if path.exists() {
+        if if_not_exists {
+            return Ok(());
+        }
+        return Err(InterpreterError::FileAlreadyExists(target.to_owned()));
+    }
+
+    //create any missing parent directories first...
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .map_err(|e| InterpreterError::CannotCreateFile(target.to_owned(), e))?;
+        }
+    }
+
+    // create_new so we never clobber a file that appeared after the exists() check
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists && if_not_exists => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(InterpreterError::FileAlreadyExists(target.to_owned()))
+        }
+        Err(e) => return Err(InterpreterError::CannotCreateFile(target.to_owned(), e)),
+    };
+    for line in content {
+        writeln!(file, "{}", line)
+            .map_err(|e| InterpreterError::CannotCreateFile(target.to_owned(), e))?;
+    }
+
+    Ok(())
+}
+
+// the file after `>` (overwrite) or `>>` (append) that a SELECT writes its results to
+fn open_export(export: &Export, sources: &[Input]) -> Result<Box<dyn Write>, InterpreterError> {
+    let (target, append) = match export {
+        Export::Overwrite(target) => (target, false),
+        Export::Append(target) => (target, true),
+    };
+    let path = Path::new(target);
+    if target.is_empty() {
+        return Err(InterpreterError::InvalidOrUnavailableFile(target.clone()));
+    }
+    if !append
+        && sources
+            .iter()
+            .filter_map(Input::path)
+            .any(|source| is_same_file(path, source))
+    {
+        return Err(InterpreterError::ExportOverwritesSource(target.clone()));
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| InterpreterError::CannotWriteFile(target.clone(), e))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+fn get_n_from_token(token: &str) -> usize {
     let re = Regex::new(r"\d+(\.\d+)?").unwrap();
     let mut total: usize = 0;
     // Find all matches in the text
-    let matches: Vec<&str> = re.find_iter(token.as_str()).map(|m| m.as_str()).collect();
+    let matches: Vec<&str> = re.find_iter(token).map(|m| m.as_str()).collect();
 
     // Print the matched numbers
     for num in matches {
@@ -201,6 +573,33 @@ fn get_n_from_token(token: &String) -> usize {
     total
 }
 
+fn get_quantity_from_token(token: &Token) -> Quantity {
+    match token {
+        Token::Star => Quantity::All,
+        Token::Top(s) => Quantity::Top(get_n_from_token(s)),
+        Token::Tail(s) => Quantity::Tail(get_n_from_token(s)),
+        Token::Lines(s) => {
+            let (first, last) = get_range_from_token(s);
+            Quantity::Lines(first, last)
+        }
+
+        _ => unreachable!(),
+    }
+}
+
+// LINES(100..200) is inclusive on both ends, like "lines 100 to 200"
+fn get_range_from_token(token: &str) -> (usize, usize) {
+    let re = Regex::new(r"(\d+)\.\.(\d+)").unwrap();
+    match re.captures(token) {
+        Some(captures) => (
+            captures[1].parse().unwrap_or(0),
+            captures[2].parse().unwrap_or(usize::MAX),
+        ),
+        None => (0, usize::MAX),
+    }
+}
+
+/// Why a statement could not run. Whatever it had already written to its sink or to files stays.
 #[derive(Debug, Error)]
 pub enum InterpreterError {
     #[error("Invalid or Unavailable. Something is preventing read of this file '{0}' ")]
@@ -209,6 +608,71 @@ pub enum InterpreterError {
     #[error("Invalid Query Token. Require '{0}'")]
     InvalidQueryToken(String),
 
+    #[error("File '{0}' already exists. Use CREATE IF NOT EXISTS to skip existing files")]
+    FileAlreadyExists(String),
+
+    #[error("Cannot create file '{0}': {1}")]
+    CannotCreateFile(String, std::io::Error),
+
+    #[error("Cannot write file '{0}': {1}")]
+    CannotWriteFile(String, std::io::Error),
+
+    #[error("Line {0} is out of range for file '{1}'")]
+    LineOutOfRange(usize, String),
+
+    #[error("No line containing '{0}' in file '{1}'")]
+    PatternNotFound(String, String),
+
+    #[error("Exporting to '{0}' would overwrite the file being queried. Use >> to append instead")]
+    ExportOverwritesSource(String),
+
+    #[error("Invalid glob pattern '{0}': {1}")]
+    InvalidGlob(String, String),
+
+    #[error("Cannot walk directory '{0}': {1}")]
+    WalkFailed(String, String),
+
+    #[error("No files match '{0}'")]
+    NoMatchingFiles(String),
+
+    #[error(
+        "'{0}' is not an encoding, try \"utf-8\", \"utf-8-lossy\", \"latin1\" or \"utf-16le\""
+    )]
+    UnknownEncoding(String),
+
+    #[error("{0} is not valid {1}, the first invalid byte sequence is at byte {2} (ENCODING \"utf-8-lossy\" reads it anyway)")]
+    InvalidEncoding(String, String, u64),
+
+    #[error("FOLLOW needs a single file to watch, '{0}' is not one")]
+    CannotFollow(String),
+
+    #[error("No column '{0}', the columns are {1}")]
+    UnknownColumn(String, String),
+
+    #[error("'{0}' is a column, but only FROM CSV, TSV, JSON, NDJSON, PARSE REGEX or FORMAT inputs have columns")]
+    NoColumns(String),
+
+    #[error("'{0}' is not a log format, try {1}")]
+    UnknownFormat(String, String),
+
+    #[error("PARSE REGEX '{0}' has no named groups to make columns of, like (?P<name>...)")]
+    NoNamedGroups(String),
+
+    #[error("'{0}' is not a path, try $.name, $.items[0] or $.items[*].id")]
+    InvalidPath(String),
+
+    #[error("Malformed JSON in {0} on line {1}: {2}")]
+    InvalidJson(String, usize, String),
+
+    #[error("A query produced more than {0} rows, the limit of this session")]
+    TooManyRows(usize),
+
+    #[error("This session is read-only, the script would write to '{0}'")]
+    ReadOnly(String),
+
+    #[error("Invalid regular expression '{0}': {1}")]
+    InvalidRegex(String, regex::Error),
+
     #[error("IO Error: {0}")]
     IOError(#[from] std::io::Error),
 }
@@ -219,3 +683,522 @@ pub enum InterpreterError {
 //         //InterpreterError::InvalidQueryToken(error.to_string())
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::{Count, ResultRow};
+
+    fn run(statement: &Statement) -> Result<QueryResult, InterpreterError> {
+        let mut collector = Collector::default();
+        execute_statement(statement, &mut collector, Options::default())?;
+        Ok(collector.results.remove(0))
+    }
+
+    fn statement(script: &str) -> Statement {
+        crate::parse(script).unwrap().remove(0)
+    }
+
+    #[test]
+    fn it_can_create_a_file_with_content_and_missing_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("nested/dir/new.txt");
+        let statement = Statement::CreateStatement {
+            target: target.to_string_lossy().into_owned(),
+            if_not_exists: false,
+            content: vec!["line1".to_owned(), "line2".to_owned()],
+        };
+
+        run(&statement).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "line1\nline2\n");
+    }
+
+    #[test]
+    fn it_refuses_to_create_an_existing_file_unless_if_not_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("existing.txt");
+        fs::write(&target, "keep me\n").unwrap();
+        let target = target.to_string_lossy().into_owned();
+
+        let create = Statement::CreateStatement {
+            target: target.clone(),
+            if_not_exists: false,
+            content: vec![],
+        };
+        assert!(matches!(
+            run(&create),
+            Err(InterpreterError::FileAlreadyExists(_))
+        ));
+
+        let create_if_not_exists = Statement::CreateStatement {
+            target: target.clone(),
+            if_not_exists: true,
+            content: vec!["overwritten".to_owned()],
+        };
+        run(&create_if_not_exists).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep me\n");
+    }
+
+    #[test]
+    fn it_can_replace_with_capture_groups_where_and_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("app.log");
+        fs::write(
+            &target,
+            "login user=ann\nlogout user=bob\nlogin user=cid user=dan\n",
+        )
+        .unwrap();
+        let target = target.to_string_lossy().into_owned();
+
+        let replaced = replace_in_file(
+            &target,
+            r"user=(?P<name>\w+)",
+            "user=${name}_old",
+            Some(&Expression::Predicate {
+                conditional: Token::Like,
+                target: "login".to_owned(),
+                escape: None,
+            }),
+            Some(2),
+        )
+        .unwrap();
+
+        assert_eq!(replaced, 2);
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "login user=ann_old\nlogout user=bob\nlogin user=cid_old user=dan\n"
+        );
+        assert_eq!(
+            replace_in_file(&target, r"user=(\w+)", "who=$1", None, None).unwrap(),
+            4
+        );
+
+        //a column on a plain line is a typo, and the file is left alone
+        let before = fs::read_to_string(&target).unwrap();
+        assert!(matches!(
+            run(&statement(&format!(r#"REPLACE "{}" REGEX "o" "0" WHERE typo = """#, target))),
+            Err(InterpreterError::NoColumns(column)) if column == "typo"
+        ));
+        assert_eq!(fs::read_to_string(&target).unwrap(), before);
+    }
+
+    #[test]
+    fn it_can_delete_matching_lines_with_top_and_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("app.log");
+        fs::write(
+            &target,
+            "debug 1\ninfo 2\ndebug 3\ndebug 4\ninfo 5\ndebug 6\n",
+        )
+        .unwrap();
+        let target = target.to_string_lossy().into_owned();
+        let debug = Expression::Predicate {
+            conditional: Token::Like,
+            target: "debug".to_owned(),
+            escape: None,
+        };
+
+        assert_eq!(
+            delete_from_file(&target, Some(&Token::Top("TOP(1)".to_owned())), &debug).unwrap(),
+            1
+        );
+        assert_eq!(
+            delete_from_file(&target, Some(&Token::Tail("TAIL(1)".to_owned())), &debug).unwrap(),
+            1
+        );
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "info 2\ndebug 3\ndebug 4\ninfo 5\n"
+        );
+
+        assert_eq!(delete_from_file(&target, None, &debug).unwrap(), 2);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "info 2\ninfo 5\n");
+
+        assert!(matches!(
+            run(&statement(&format!(r#"DELETE FROM "{}" WHERE typo != "y""#, target))),
+            Err(InterpreterError::NoColumns(column)) if column == "typo"
+        ));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "info 2\ninfo 5\n");
+    }
+
+    #[test]
+    fn it_can_export_several_statements_into_one_file_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("app.log");
+        fs::write(&source, "one\ntwo words\nthree\n").unwrap();
+        let source = source.to_string_lossy().into_owned();
+        let output = dir.path().join("out.txt").to_string_lossy().into_owned();
+        let program = crate::parse(&format!(
+            r#"SELECT TOP(1) FROM "{0}" > "{1}"; SELECT COUNTW * FROM "{0}" >> "{1}"; SELECT TAIL(1) FROM "{0}" >> "{1}";"#,
+            source, output
+        ))
+        .unwrap();
+        interprete(&program).unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "one\n4\nthree\n");
+
+        //running the same script again starts over because the first export overwrites
+        interprete(&program).unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "one\n4\nthree\n");
+
+        assert!(matches!(
+            run(&statement(&format!(
+                r#"SELECT * FROM "{0}" > "{0}""#,
+                source
+            ))),
+            Err(InterpreterError::ExportOverwritesSource(_))
+        ));
+    }
+
+    #[test]
+    fn it_can_select_top_and_tail_beyond_the_end_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("short.log");
+        fs::write(&source, "one\ntwo\n").unwrap();
+        let output = dir.path().join("out.txt").to_string_lossy().into_owned();
+        let select = |quantity: &str, offset: &str| {
+            let script = format!(
+                r#"SELECT {} FROM "{}" {} >> "{}""#,
+                quantity,
+                source.display(),
+                offset,
+                output
+            );
+            run(&statement(&script))
+        };
+
+        select("TOP(100)", "").unwrap();
+        select("TAIL(100)", "OFFSET 1").unwrap();
+        select("TOP(100)", "OFFSET 100").unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "one\ntwo\ntwo\n");
+    }
+
+    #[test]
+    fn it_can_select_from_many_files_with_a_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+        fs::create_dir(dir.path().join("logs/old")).unwrap();
+        fs::write(dir.path().join("logs/a.log"), "ERROR one\nok\n").unwrap();
+        fs::write(
+            dir.path().join("logs/b.log"),
+            "ok\nERROR two\nERROR three\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("logs/old/c.log"), "ERROR four\n").unwrap();
+        fs::write(dir.path().join("logs/notes.txt"), "ERROR not a log\n").unwrap();
+        let logs = dir.path().join("logs").to_string_lossy().into_owned();
+        let output = dir.path().join("out.txt").to_string_lossy().into_owned();
+        let select = |counter: &str, pattern: &str| {
+            let script = format!(
+                r#"SELECT {} * FROM "{}/{}" WHERE LIKE "ERROR" >> "{}""#,
+                counter, logs, pattern, output
+            );
+            run(&statement(&script))
+        };
+
+        select("", "*.log").unwrap();
+        select("COUNTL", "**/*.log").unwrap();
+
+        let expected = [
+            format!("{}/a.log:ERROR one", logs),
+            format!("{}/b.log:ERROR two", logs),
+            format!("{}/b.log:ERROR three", logs),
+            format!("{}/a.log:1", logs),
+            format!("{}/b.log:2", logs),
+            format!("{}/old/c.log:1", logs),
+            "total:4".to_owned(),
+        ];
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            expected.join("\n") + "\n"
+        );
+
+        //one page over all the files, not a page from each
+        let page = |paging: &str| {
+            let script = format!(
+                r#"SELECT * FROM "{}/**/*.log" WHERE LIKE "ERROR" {}"#,
+                logs, paging
+            );
+            let rows = run(&statement(&script)).unwrap().rows;
+            rows.into_iter().map(|row| row.line).collect::<Vec<_>>()
+        };
+        assert_eq!(page("LIMIT 1"), vec!["ERROR one"]);
+        assert_eq!(page("LIMIT 2 OFFSET 2"), vec!["ERROR three", "ERROR four"]);
+        //and one sort over all of them, before TOP
+        let script = format!(
+            r#"SELECT TOP(2) FROM "{}/**/*.log" ORDER BY LENGTH DESC"#,
+            logs
+        );
+        let rows = run(&statement(&script)).unwrap().rows;
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.file.as_str(), row.line.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (format!("{}/b.log", logs).as_str(), "ERROR three"),
+                (format!("{}/old/c.log", logs).as_str(), "ERROR four")
+            ]
+        );
+
+        assert!(matches!(
+            select("", "*.missing"),
+            Err(InterpreterError::NoMatchingFiles(_))
+        ));
+    }
+
+    #[test]
+    fn it_refuses_to_follow_many_files_before_any_results() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "one\n").unwrap();
+        fs::write(dir.path().join("b.log"), "two\n").unwrap();
+        let script = format!(
+            r#"SELECT TAIL(1) FOLLOW FROM "{}/*.log""#,
+            dir.path().display()
+        );
+
+        let mut collector = Collector::default();
+        assert!(matches!(
+            execute_statement(&statement(&script), &mut collector, Options::default()),
+            Err(InterpreterError::CannotFollow(_))
+        ));
+        assert!(collector.results.is_empty());
+    }
+
+    #[test]
+    fn it_returns_the_results_of_every_statement() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("app.log");
+        fs::write(&source, "ERROR one\nok\nERROR two\n").unwrap();
+        let source = source.to_string_lossy().into_owned();
+        let program = crate::parse(&format!(
+            r#"SELECT * FROM "{0}" WHERE LIKE "ERROR"; SELECT COUNTL * FROM "{0}" WHERE LIKE "ERROR"; DELETE FROM "{0}" WHERE LIKE "ok";"#,
+            source
+        ))
+        .unwrap();
+
+        let results = interprete(&program).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].results, Results::Rows);
+        //line numbers are there even without WITH LINENO
+        assert_eq!(
+            results[0].rows,
+            vec![
+                ResultRow {
+                    file: source.clone(),
+                    number: 1,
+                    line: "ERROR one".to_owned(),
+                    fields: Fields::default()
+                },
+                ResultRow {
+                    file: source.clone(),
+                    number: 3,
+                    line: "ERROR two".to_owned(),
+                    fields: Fields::default()
+                },
+            ]
+        );
+        assert_eq!(results[1].results, Results::Counts("lines"));
+        assert_eq!(
+            results[1].counts,
+            vec![Count {
+                file: Some(source.clone()),
+                count: 2
+            }]
+        );
+        assert_eq!(results[2].results, Results::Counts("deleted"));
+        assert_eq!(
+            results[2].counts,
+            vec![Count {
+                file: Some(source.clone()),
+                count: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn it_can_select_columns_from_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("orders.csv");
+        fs::write(&source, "name,amount\nann,120\nbob,80\n\"cid, jr\",1000\n").unwrap();
+        let source = source.to_string_lossy().into_owned();
+        let select = |columns: &str, column: &str| {
+            run(&statement(&format!(
+                r#"SELECT {} FROM CSV "{}" HEADER WHERE {} > 100"#,
+                columns, source, column
+            )))
+        };
+
+        let result = select("name", "amount").unwrap();
+        let names: Vec<&str> = result.rows.iter().map(|row| row.line.as_str()).collect();
+        assert_eq!(names, vec!["ann", "\"cid, jr\""]);
+        assert_eq!(result.rows[1].number, 4);
+        assert_eq!(result.rows[1].fields.get("name"), Some(&"cid, jr".into()));
+        assert_eq!(result.rows[1].fields.get("amount"), None);
+
+        assert!(matches!(
+            select("*", "amout"),
+            Err(InterpreterError::UnknownColumn(column, _)) if column == "amout"
+        ));
+    }
+
+    #[test]
+    fn it_can_order_by_a_column_it_does_not_select() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("orders.csv");
+        fs::write(&source, "name,amount\nann,120\nbob,80\ncid,120\ndan,9\n").unwrap();
+        let query = |order: &str| {
+            let script = format!(
+                r#"SELECT TOP(3) name FROM CSV "{}" HEADER ORDER BY {}"#,
+                source.display(),
+                order
+            );
+            let result = run(&statement(&script)).unwrap();
+            result
+                .rows
+                .into_iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>()
+        };
+
+        //ties keep file order, DESC too
+        assert_eq!(query("amount DESC NUMERIC"), vec!["ann", "cid", "bob"]);
+        assert_eq!(query("amount"), vec!["ann", "cid", "bob"]);
+        assert_eq!(query("amount NUMERIC"), vec!["dan", "bob", "ann"]);
+        assert!(matches!(
+            run(&statement(r#"SELECT * FROM "a.log" ORDER BY amount"#)),
+            Err(InterpreterError::NoColumns(column)) if column == "amount"
+        ));
+    }
+
+    #[test]
+    fn it_selects_every_member_of_json_and_logfmt_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let events = dir.path().join("events.log");
+        fs::write(&events, "{\"user\": {\"name\": \"ann\"}, \"status\": 500}\n{\"user\": {\"name\": \"bob\"}, \"status\": 200}\n").unwrap();
+        let app = dir.path().join("app.log");
+        fs::write(&app, "level=info msg=up\nlevel=error msg=down code=7\n").unwrap();
+        let select = |script: String| run(&statement(&script)).unwrap().rows;
+
+        //the columns WHERE and ORDER BY needed are not among them
+        let rows = select(format!(
+            r#"SELECT * FROM NDJSON "{}" WHERE $.user.name = 'ann' ORDER BY status"#,
+            events.display()
+        ));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].fields.names(), ["user", "status"]);
+        assert_eq!(rows[0].fields.get("status"), Some(&500.into()));
+
+        let rows = select(format!(
+            r#"SELECT * FROM "{}" FORMAT logfmt WHERE level = 'error'"#,
+            app.display()
+        ));
+        assert_eq!(rows[0].fields.names(), ["level", "msg", "code"]);
+        assert_eq!(rows[0].fields.get("code"), Some(&7.into()));
+    }
+
+    #[test]
+    fn it_can_use_columns_named_like_keywords_when_they_are_quoted() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("errors.csv");
+        fs::write(
+            &source,
+            "line,length,first name\n10,3,ann\n7,12,bob\n42,5,cid\n",
+        )
+        .unwrap();
+        let script = format!(
+            r#"SELECT `first name`, `line` FROM CSV "{}" HEADER WHERE `line` > 8 ORDER BY `length` DESC NUMERIC"#,
+            source.display()
+        );
+
+        let result = run(&statement(&script)).unwrap();
+        let lines: Vec<&str> = result.rows.iter().map(|row| row.line.as_str()).collect();
+        assert_eq!(lines, vec!["cid,42", "ann,10"]);
+        assert_eq!(result.rows[0].fields.get("first name"), Some(&"cid".into()));
+    }
+
+    #[test]
+    fn it_can_select_in_other_encodings_and_refuse_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let latin1 = dir.path().join("latin1.log");
+        fs::write(&latin1, b"caf\xe9\nna\xefve\nr\xe9sum\xe9\n").unwrap();
+        let utf16 = dir.path().join("utf16.log");
+        fs::write(&utf16, b"\xff\xfeo\0n\0e\0\n\0t\0w\0o\0\n\0").unwrap();
+        let output = dir.path().join("out.txt").to_string_lossy().into_owned();
+        let select = |quantity: &str, source: &Path, encoding: &str| {
+            let script = format!(
+                r#"SELECT {} FROM "{}" {} >> "{}""#,
+                quantity,
+                source.display(),
+                encoding,
+                output
+            );
+            run(&statement(&script))
+        };
+
+        select("*", &latin1, r#"ENCODING "latin1""#).unwrap();
+        select("TAIL(1)", &latin1, r#"ENCODING "latin1""#).unwrap();
+        select("TAIL(1)", &latin1, r#"ENCODING "utf-8-lossy""#).unwrap();
+        //the BOM says UTF-16, whatever the query says
+        select("TAIL(1)", &utf16, "").unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "café\nnaïve\nrésumé\nrésumé\nr\u{fffd}sum\u{fffd}\ntwo\n"
+        );
+
+        assert!(matches!(
+            select("*", &latin1, ""),
+            Err(InterpreterError::InvalidEncoding(_, _, 3))
+        ));
+        assert!(matches!(
+            select("TAIL(1)", &latin1, ""),
+            Err(InterpreterError::InvalidEncoding(_, _, 12))
+        ));
+        assert!(matches!(
+            select("*", &latin1, r#"ENCODING "klingon""#),
+            Err(InterpreterError::UnknownEncoding(_))
+        ));
+    }
+
+    #[test]
+    fn it_can_update_a_file_at_every_placement() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("app.ini");
+        fs::write(&target, "[main]\nport=80\n[tail]\n").unwrap();
+        let target = target.to_string_lossy().into_owned();
+        let update = |content: &str, placement: Option<Placement>| Statement::UpdateStatement {
+            target: target.clone(),
+            content: vec![content.to_owned()],
+            placement,
+        };
+
+        run(&update("appended", None)).unwrap();
+        run(&update("first", Some(Placement::AtLine(1)))).unwrap();
+        run(&update(
+            "debug=true",
+            Some(Placement::After("[main]".to_owned())),
+        ))
+        .unwrap();
+        run(&update(
+            "[extra]",
+            Some(Placement::Before("[tail]".to_owned())),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "first\n[main]\ndebug=true\nport=80\n[extra]\n[tail]\nappended\n"
+        );
+        assert!(matches!(
+            run(&update("x", Some(Placement::After("missing".to_owned())))),
+            Err(InterpreterError::PatternNotFound(..))
+        ));
+        assert!(matches!(
+            run(&update("x", Some(Placement::AtLine(100)))),
+            Err(InterpreterError::LineOutOfRange(..))
+        ));
+        assert!(matches!(
+            run(&update("x", Some(Placement::AtLine(0)))),
+            Err(InterpreterError::LineOutOfRange(..))
+        ));
+    }
+}
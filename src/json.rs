@@ -0,0 +1,258 @@
+use std::{io::BufRead, sync::Arc};
+
+use serde_json::Value;
+
+use crate::{
+    encoding,
+    interpreter::{InterpreterError, Row},
+    record::Fields,
+};
+
+// A path into a JSON record, $.user.name, $.items[0] or $.items[*].id. A plain column name is
+// the top-level member of that name, so `level` is $.level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Member(String),
+    Index(usize),
+    // [*], every element of an array or every member of an object
+    All,
+}
+
+impl Path {
+    pub fn parse(path: &str) -> Result<Self, InterpreterError> {
+        let invalid = || InterpreterError::InvalidPath(path.to_owned());
+        let Some(mut rest) = path.strip_prefix('$') else {
+            return Ok(Self {
+                steps: vec![Step::Member(path.to_owned())],
+            });
+        };
+
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(invalid());
+                }
+                steps.push(Step::Member(after[..end].to_owned()));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(invalid)?;
+                let inside = &after[..end];
+                steps.push(match inside {
+                    "*" => Step::All,
+                    //["a key"] or ['a key'], for names that are not identifiers
+                    _ if inside.len() >= 2
+                        && (inside.starts_with('"') && inside.ends_with('"')
+                            || inside.starts_with('\'') && inside.ends_with('\'')) =>
+                    {
+                        Step::Member(inside[1..inside.len() - 1].to_owned())
+                    }
+                    _ => Step::Index(inside.parse().map_err(|_| invalid())?),
+                });
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid());
+            }
+        }
+        Ok(Self { steps })
+    }
+
+    // whether the path can match more than one value
+    pub fn is_wildcard(&self) -> bool {
+        self.steps.contains(&Step::All)
+    }
+
+    // The value at the path, null when there is none. A path with [*] gives every value it
+    // matches as an array, an empty one when nothing matched.
+    pub fn select(&self, value: &Value) -> Value {
+        let mut current = vec![value];
+        for step in &self.steps {
+            current = current
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value> {
+                    match (step, value) {
+                        (Step::Member(name), Value::Object(members)) => {
+                            members.get(name).into_iter().collect()
+                        }
+                        (Step::Index(i), Value::Array(items)) => {
+                            items.get(*i).into_iter().collect()
+                        }
+                        (Step::All, Value::Array(items)) => items.iter().collect(),
+                        (Step::All, Value::Object(members)) => members.values().collect(),
+                        _ => Vec::new(),
+                    }
+                })
+                .collect();
+        }
+        if self.is_wildcard() {
+            Value::Array(current.into_iter().cloned().collect())
+        } else {
+            current
+                .first()
+                .map_or(Value::Null, |value| (*value).clone())
+        }
+    }
+}
+
+// The columns a SELECT takes out of every JSON record, the ones it outputs and the ones its
+// WHERE looks at. SELECT * gets the top-level members of each record after them, so the record
+// is only parsed once.
+pub struct Columns {
+    names: Arc<[String]>,
+    paths: Vec<Path>,
+    members: bool,
+}
+
+impl Columns {
+    pub fn new(names: &[String], members: bool) -> Result<Self, InterpreterError> {
+        let mut unique: Vec<String> = Vec::new();
+        for name in names {
+            if !unique.contains(name) {
+                unique.push(name.clone());
+            }
+        }
+        Ok(Self {
+            paths: unique
+                .iter()
+                .map(|name| Path::parse(name))
+                .collect::<Result<_, _>>()?,
+            names: unique.into(),
+            members,
+        })
+    }
+
+    // how many fields of a row are columns, the members of SELECT * come after them
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn row(&self, number: usize, line: String, record: &Value) -> Row {
+        let mut values: Vec<Value> = self.paths.iter().map(|path| path.select(record)).collect();
+        let names = match record {
+            //the columns come first, so WHERE still finds a path before a member that looks like one
+            Value::Object(members) if self.members => {
+                values.extend(members.values().cloned());
+                self.names.iter().chain(members.keys()).cloned().collect()
+            }
+            _ => self.names.clone(),
+        };
+        Row {
+            number,
+            line,
+            fields: Fields::new(names, values),
+        }
+    }
+}
+
+// FROM NDJSON "events.log", one record on every line. Blank lines are skipped, anything else
+// that is not JSON is an error that says where it is.
+pub fn scan_lines<'a>(
+    reader: impl BufRead + 'a,
+    input: &'a str,
+    columns: &'a Columns,
+) -> impl Iterator<Item = Result<Row, InterpreterError>> + 'a {
+    reader.lines().enumerate().filter_map(move |(i, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(encoding::read_error(e))),
+        };
+        if line.trim().is_empty() {
+            return None;
+        }
+        Some(match serde_json::from_str(&line) {
+            Ok(record) => Ok(columns.row(i + 1, line, &record)),
+            Err(e) => Err(malformed(input, i + 1, &e)),
+        })
+    })
+}
+
+// FROM JSON "items.json", one document. Every element of a top-level array is a record, numbered
+// from 1, anything else is a single record. The whole document is read before the first row.
+pub fn scan_document<'a>(
+    reader: impl BufRead + 'a,
+    input: &'a str,
+    columns: &'a Columns,
+) -> Box<dyn Iterator<Item = Result<Row, InterpreterError>> + 'a> {
+    let document: Value = match serde_json::from_reader(reader) {
+        Ok(document) => document,
+        Err(e) if e.is_io() => {
+            return Box::new(std::iter::once(Err(encoding::read_error(e.into()))))
+        }
+        Err(e) => return Box::new(std::iter::once(Err(malformed(input, e.line(), &e)))),
+    };
+    let records = match document {
+        Value::Array(records) => records,
+        record => vec![record],
+    };
+    Box::new(
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| Ok(columns.row(i + 1, record.to_string(), &record))),
+    )
+}
+
+fn malformed(input: &str, line: usize, e: &serde_json::Error) -> InterpreterError {
+    //serde_json puts "at line 1 column 7" on the end, only the column is news
+    let message = e.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+    InterpreterError::InvalidJson(
+        input.to_owned(),
+        line,
+        format!("{} at column {}", message, e.column()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_can_select_paths() {
+        let record = json!({"user": {"name": "ann"}, "items": [{"id": 1}, {"id": 2}, {"name": "x"}], "a key": true});
+        let select = |path: &str| Path::parse(path).unwrap().select(&record);
+
+        assert_eq!(select("$.user.name"), json!("ann"));
+        assert_eq!(select("user"), json!({"name": "ann"}));
+        assert_eq!(select("$.items[1].id"), json!(2));
+        assert_eq!(select("$.items[*].id"), json!([1, 2]));
+        assert_eq!(select(r#"$["a key"]"#), json!(true));
+        assert_eq!(select("$.items[7].id"), Value::Null);
+        assert_eq!(select("$.nope[*]"), json!([]));
+        assert!(matches!(
+            Path::parse("$.items[x]"),
+            Err(InterpreterError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            Path::parse("$..id"),
+            Err(InterpreterError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn it_reports_malformed_lines_with_their_number() {
+        let columns = Columns::new(&["$.level".to_owned()], false).unwrap();
+        let input = "{\"level\": \"info\"}\n\n{\"level\": \"error\"}\n{\"level\": oops}\n";
+        let rows: Vec<_> = scan_lines(input.as_bytes(), "events.log", &columns).collect();
+
+        assert_eq!(rows.len(), 3);
+        let row = rows[1].as_ref().unwrap();
+        assert_eq!(
+            (row.number, row.fields.get("$.level")),
+            (3, Some(&json!("error")))
+        );
+        assert!(matches!(
+            &rows[2],
+            Err(InterpreterError::InvalidJson(input, 4, message)) if input == "events.log" && message.ends_with("at column 11")
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+//! FQL as a library, for running queries in-process rather than through the `fql` binary:
+//!
+//! ```
+//! # let dir = tempfile::tempdir().unwrap();
+//! # std::fs::write(dir.path().join("app.log"), "ERROR one\nok\nERROR two\n").unwrap();
+//! # std::env::set_current_dir(dir.path()).unwrap();
+//! let program = fql::parse(r#"SELECT COUNTL * FROM "app.log" WHERE LIKE "ERROR""#)?;
+//! let results = fql::interprete(&program)?;
+//! assert_eq!(results[0].counts[0].count, 2);
+//! # Ok::<(), fql::Error>(())
+//! ```
+//!
+//! or, with a working directory, limits and a sink of your own, through a [Session]. The binary is
+//! nothing more than a Session that prints to stdout.
+
+mod ast;
+mod capture;
+mod condition;
+mod edit;
+mod encoding;
+mod follow;
+mod interpreter;
+mod json;
+mod order;
+mod output;
+mod parser;
+mod pipeline;
+mod preset;
+mod record;
+mod result;
+mod session;
+mod source;
+mod token;
+
+use thiserror::Error;
+
+//the syntax tree and the tokens are free to change, only what a caller needs is public
+pub use ast::{Format, Statement};
+pub use interpreter::{execute, interprete, InterpreterError, Row};
+pub use output::Printer;
+pub use parser::ParseError;
+pub use record::Fields;
+pub use result::{Collector, Count, Header, QueryResult, ResultRow, Results, Sink};
+pub use session::Session;
+
+/// a script as statements, ready to execute
+pub fn parse(script: &str) -> Result<Vec<Statement>, ParseError> {
+    parser::parse(token::generate(script.trim()))
+}
+
+/// everything that can go wrong running a script, from its text to its last statement
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Syntax error: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("{0}")]
+    Interpreter(#[from] InterpreterError),
+}
@@ -1,30 +1,132 @@
-use std::{fs, env::args};
+use std::{
+    env::args,
+    fs,
+    io::{self, Read},
+    process, slice,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
+use fql::{Format, Printer, Session, Statement};
 
-mod token;
-mod parser;
-mod interpreter;
-mod ast;
+const USAGE: &str = "usage: fql <script.fql>    run a script file
+       fql -e '<query>'     run the query given on the command line
+       fql [-]              read the script from stdin
+
+       --format json|ndjson|csv|table|text
+                            how to write the results of every SELECT without an OUTPUT";
 
 fn main() {
-    println!("Hello, world!");
-    let file = args().nth(1).unwrap();
-    let contents = fs::read_to_string(file).unwrap();
-
-    let tokens = token::generate(contents.as_str().trim());
-
-    dbg!(&tokens);
-
-    match parser::parse(tokens) {
-        Ok(ast) => {
-            match interpreter::interprete(ast) {
-                Ok(_) => {
-                    
-                },
-                Err(e) => eprintln!("Error occurred while parsing token >>> {}", e) ,//e.print(),
-            };
-        },
-        Err(e) => eprintln!("Error occurred while parsing token >>> {}", e) , // e.print(),
+    let mut args: Vec<String> = args().skip(1).collect();
+    let format = match take_format(&mut args) {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let (contents, script_from_stdin) = match read_script(&args) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let program = match fql::parse(&contents) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("Syntax error: {}", e);
+            process::exit(1);
+        }
     };
+    //stdin can only be read once, either for the script or for the data...
+    if script_from_stdin && program.iter().any(Statement::reads_stdin) {
+        eprintln!(
+            "Cannot read both the script and FROM STDIN from stdin, use fql -e '<query>' instead"
+        );
+        process::exit(2);
+    }
+
+    //Ctrl-C only ends a FOLLOW and the rest of the script still runs, anywhere else it stops fql as usual
+    let stop = Arc::new(AtomicBool::new(false));
+    let following = Arc::new(AtomicBool::new(false));
+    if program.iter().any(Statement::follows) {
+        let (stop, following) = (stop.clone(), following.clone());
+        //without a handler Ctrl-C just kills the process, so following still works, only less cleanly
+        let _ = ctrlc::set_handler(move || {
+            if following.load(Ordering::SeqCst) {
+                stop.store(true, Ordering::SeqCst);
+            } else {
+                process::exit(130);
+            }
+        });
+    }
+
+    let mut session =
+        Session::new(Printer::new(Box::new(io::stdout().lock()))).stop_flag(stop.clone());
+    if let Some(format) = format {
+        session = session.format(format);
+    }
+    //one statement at a time, so the handler knows when a FOLLOW is running
+    for statement in &program {
+        stop.store(false, Ordering::SeqCst);
+        following.store(statement.follows(), Ordering::SeqCst);
+        let result = session.execute(slice::from_ref(statement));
+        following.store(false, Ordering::SeqCst);
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// --format can go anywhere on the command line, it is taken out before the rest is looked at
+fn take_format(args: &mut Vec<String>) -> Result<Option<Format>, String> {
+    let Some(i) = args
+        .iter()
+        .position(|arg| arg == "--format" || arg.starts_with("--format="))
+    else {
+        return Ok(None);
+    };
+    let flag = args.remove(i);
+    let name = match flag.strip_prefix("--format=") {
+        Some(name) => name.to_owned(),
+        None if i < args.len() => args.remove(i),
+        None => return Err("--format needs a format".to_owned()),
+    };
+    match name.to_ascii_lowercase().as_str() {
+        "text" => Ok(Some(Format::Text)),
+        "json" => Ok(Some(Format::Json)),
+        "ndjson" => Ok(Some(Format::Ndjson)),
+        "csv" => Ok(Some(Format::Csv)),
+        "table" => Ok(Some(Format::Table)),
+        _ => Err(format!("Unknown format '{}'", name)),
+    }
+}
+
+// the script text, and whether it came from stdin
+fn read_script(args: &[String]) -> Result<(String, bool), String> {
+    match args {
+        [] => read_stdin().map(|script| (script, true)),
+        [dash] if dash == "-" => read_stdin().map(|script| (script, true)),
+        [flag, query] if flag == "-e" => Ok((query.clone(), false)),
+        [flag] if flag == "-e" => Err("-e needs a query".to_owned()),
+        [file] => fs::read_to_string(file)
+            .map(|script| (script, false))
+            .map_err(|e| format!("Cannot read script '{}': {}", file, e)),
+        _ => Err("Too many arguments".to_owned()),
+    }
+}
 
+fn read_stdin() -> Result<String, String> {
+    let mut script = String::new();
+    io::stdin()
+        .read_to_string(&mut script)
+        .map_err(|e| format!("Cannot read script from stdin: {}", e))?;
+    Ok(script)
 }
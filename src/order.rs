@@ -0,0 +1,186 @@
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use crate::{
+    ast::{Collation, OrderBy, SortKey},
+    interpreter::Row,
+    record::text,
+};
+
+// What a row is sorted by, worked out once per row and not once per comparison.
+enum Key {
+    Number(f64),
+    Text(String),
+    // a column the row does not have (null), or NUMERIC on something that is not a number
+    Missing,
+}
+
+// ORDER BY: sort the rows that got through WHERE, each one in whatever `row` finds it in. The
+// sort is stable and DESC turns the comparison around instead of the result, so rows that tie
+// stay in file order either way. Rows without a value come last, ASC or DESC.
+pub fn sort<T>(items: Vec<T>, order: &OrderBy, row: impl Fn(&T) -> &Row) -> Vec<T> {
+    let mut keyed: Vec<(Key, T)> = items
+        .into_iter()
+        .map(|item| (key(row(&item), order), item))
+        .collect();
+    keyed.sort_by(|(left, _), (right, _)| compare(left, right, order));
+    keyed.into_iter().map(|(_, item)| item).collect()
+}
+
+fn key(row: &Row, order: &OrderBy) -> Key {
+    let value = match &order.key {
+        SortKey::Line => Value::String(row.line.clone()),
+        SortKey::Length => Value::from(row.line.chars().count()),
+        SortKey::Column(name) => row.fields.get(name).cloned().unwrap_or(Value::Null),
+    };
+    match (value, order.collation) {
+        (Value::Null, _) => Key::Missing,
+        (value, Collation::Numeric) => match value.as_f64() {
+            Some(n) => Key::Number(n),
+            None => text(&value)
+                .trim()
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .map_or(Key::Missing, Key::Number),
+        },
+        (value, _) => match value.as_f64() {
+            Some(n) => Key::Number(n),
+            None => Key::Text(text(&value).into_owned()),
+        },
+    }
+}
+
+fn compare(left: &Key, right: &Key, order: &OrderBy) -> Ordering {
+    let ordering = match (left, right) {
+        (Key::Missing, Key::Missing) => return Ordering::Equal,
+        (Key::Missing, _) => return Ordering::Greater,
+        (_, Key::Missing) => return Ordering::Less,
+        (Key::Number(l), Key::Number(r)) => l.total_cmp(r),
+        (Key::Number(_), Key::Text(_)) => Ordering::Less,
+        (Key::Text(_), Key::Number(_)) => Ordering::Greater,
+        (Key::Text(l), Key::Text(r)) if order.collation == Collation::Natural => natural(l, r),
+        (Key::Text(l), Key::Text(r)) => l.cmp(r),
+    };
+    if order.descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+// "file2" < "file10": runs of digits compare by their value, everything else character by
+// character. "007" and "7" are equal, and the stable sort keeps them in file order.
+fn natural(left: &str, right: &str) -> Ordering {
+    let (mut left, mut right) = (left, right);
+    loop {
+        let (l, r) = match (left.chars().next(), right.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) => (l, r),
+        };
+        if l.is_ascii_digit() && r.is_ascii_digit() {
+            let (l_digits, l_rest) = split_digits(left);
+            let (r_digits, r_rest) = split_digits(right);
+            let (l_digits, r_digits) = (
+                l_digits.trim_start_matches('0'),
+                r_digits.trim_start_matches('0'),
+            );
+            //no leading zeros, so the longer number is the bigger one
+            let ordering = l_digits
+                .len()
+                .cmp(&r_digits.len())
+                .then_with(|| l_digits.cmp(r_digits));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (left, right) = (l_rest, r_rest);
+        } else {
+            if l != r {
+                return l.cmp(&r);
+            }
+            (left, right) = (&left[l.len_utf8()..], &right[r.len_utf8()..]);
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(lines: &[&str], key: SortKey, descending: bool, collation: Collation) -> Vec<String> {
+        let rows = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| Row::new(i + 1, line.to_string()))
+            .collect();
+        let order = OrderBy {
+            key,
+            descending,
+            collation,
+        };
+        sort(rows, &order, |row| row)
+            .into_iter()
+            .map(|row| row.line)
+            .collect()
+    }
+
+    #[test]
+    fn it_keeps_ties_in_file_order() {
+        let lines = ["bb", "a", "cc", "d", "eee"];
+        assert_eq!(
+            sorted(&lines, SortKey::Length, true, Collation::Default),
+            vec!["eee", "bb", "cc", "a", "d"]
+        );
+        assert_eq!(
+            sorted(&lines, SortKey::Length, false, Collation::Default),
+            vec!["a", "d", "bb", "cc", "eee"]
+        );
+        assert_eq!(
+            sorted(&lines, SortKey::Line, true, Collation::Default),
+            vec!["eee", "d", "cc", "bb", "a"]
+        );
+    }
+
+    #[test]
+    fn it_can_sort_numerically_and_naturally() {
+        let lines = ["10", "9", "x", "-1.5", "100"];
+        assert_eq!(
+            sorted(&lines, SortKey::Line, false, Collation::Default),
+            vec!["-1.5", "10", "100", "9", "x"]
+        );
+        //what is not a number comes last, DESC too
+        assert_eq!(
+            sorted(&lines, SortKey::Line, false, Collation::Numeric),
+            vec!["-1.5", "9", "10", "100", "x"]
+        );
+        assert_eq!(
+            sorted(&lines, SortKey::Line, true, Collation::Numeric),
+            vec!["100", "10", "9", "-1.5", "x"]
+        );
+
+        let files = [
+            "file10.log",
+            "file2.log",
+            "file1.log",
+            "file02.log",
+            "fila.log",
+        ];
+        assert_eq!(
+            sorted(&files, SortKey::Line, false, Collation::Natural),
+            vec![
+                "fila.log",
+                "file1.log",
+                "file2.log",
+                "file02.log",
+                "file10.log"
+            ]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use std::io::{self, Write};
+
+use crate::{
+    ast::Format,
+    interpreter::{InterpreterError, Row},
+    record::{quote, text},
+    result::{Header, Results, Sink},
+};
+
+/// The Sink that writes results out, to stdout or to the file of an export, in the OUTPUT format
+/// of each statement. Text is what FQL always printed: the line (with "file:" and "lineno:" in
+/// front when asked for), or the bare count. Every other format is meant for other programs, so
+/// there every row carries its file and line number and every count is a named field, always.
+/// TABLE has to see every row before it can align the columns, so it is the one format that
+/// holds on to the results until the end.
+///
+/// Rows with columns (FROM CSV...) have a field per column instead of "line". CSV and TABLE take
+/// their columns from the first row, JSON has every field of every row, with its JSON type.
+pub struct Printer {
+    out: Box<dyn Write>,
+    header: Header,
+    printed: usize,
+    names: Vec<String>,
+    table: Vec<Vec<String>>,
+}
+
+impl Printer {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Self {
+            out,
+            header: Header::done(),
+            printed: 0,
+            names: Vec::new(),
+            table: Vec::new(),
+        }
+    }
+
+    fn columns(&self) -> Vec<String> {
+        let mut columns = vec!["file".to_owned()];
+        match self.header.results {
+            Results::Rows if self.names.is_empty() => {
+                columns.extend(["lineno".to_owned(), "line".to_owned()])
+            }
+            Results::Rows => {
+                columns.push("lineno".to_owned());
+                columns.extend(self.names.iter().cloned());
+            }
+            Results::Counts(name) => columns.push(name.to_owned()),
+            Results::Done => return Vec::new(),
+        }
+        columns
+    }
+
+    // before the first row or count, which is when the columns are known
+    fn start(&mut self, names: &[String]) -> io::Result<()> {
+        self.names = names.to_vec();
+        if self.header.format == Format::Csv {
+            writeln!(self.out, "{}", self.columns().join(","))?;
+        }
+        Ok(())
+    }
+
+    fn json_object(&mut self, object: &str) -> io::Result<()> {
+        match (self.header.format, self.printed) {
+            (Format::Ndjson, _) => writeln!(self.out, "{}", object),
+            (_, 0) => write!(self.out, "[\n  {}", object),
+            _ => write!(self.out, ",\n  {}", object),
+        }
+    }
+
+    // what a row has besides its file and line number, under the columns of the first row (a
+    // JSON record can lack some of them)
+    fn cells(&self, row: &Row) -> Vec<String> {
+        if row.fields.is_empty() {
+            return vec![row.line.clone()];
+        }
+        self.names
+            .iter()
+            .map(|name| {
+                row.fields
+                    .get(name)
+                    .map_or_else(String::new, |value| text(value).into_owned())
+            })
+            .collect()
+    }
+
+    fn print_table(&mut self) -> io::Result<()> {
+        let header = self.columns();
+        let mut widths: Vec<usize> = header.iter().map(|column| column.chars().count()).collect();
+        for row in &self.table {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        //numbers line up on the right, everything else on the left
+        let numeric: Vec<bool> = (0..header.len())
+            .map(|i| {
+                !self.table.is_empty()
+                    && self
+                        .table
+                        .iter()
+                        .all(|row| row.get(i).is_some_and(|cell| cell.parse::<f64>().is_ok()))
+            })
+            .collect();
+
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        for row in [&header, &rule].into_iter().chain(&self.table) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .zip(&numeric)
+                .map(|((cell, width), numeric)| match numeric {
+                    true => format!("{:>width$}", cell),
+                    false => format!("{:<width$}", cell),
+                })
+                .collect();
+            //so lines do not end in spaces, not even with empty cells at the end
+            writeln!(self.out, "{}", cells.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Printer {
+    fn begin(&mut self, header: &Header) -> Result<(), InterpreterError> {
+        self.header = *header;
+        self.printed = 0;
+        self.table.clear();
+        Ok(())
+    }
+
+    fn row(&mut self, file: &str, row: Row) -> Result<(), InterpreterError> {
+        if self.printed == 0 {
+            self.start(row.fields.names())?;
+        }
+        match self.header.format {
+            Format::Text => {
+                if self.header.prefixed {
+                    write!(self.out, "{}:", file)?;
+                }
+                if self.header.numbered {
+                    write!(self.out, "{}:", row.number)?;
+                }
+                writeln!(self.out, "{}", row.line)?;
+            }
+            Format::Json | Format::Ndjson => {
+                let mut object =
+                    format!(r#"{{"file":{},"lineno":{}"#, json_string(file), row.number);
+                if row.fields.is_empty() {
+                    object.push_str(&format!(r#","line":{}"#, json_string(&row.line)));
+                }
+                for (name, value) in row.fields.iter() {
+                    object.push_str(&format!(",{}:{}", json_string(name), value));
+                }
+                object.push('}');
+                self.json_object(&object)?;
+            }
+            Format::Csv => {
+                let mut fields = vec![quote(file, ',').into_owned(), row.number.to_string()];
+                fields.extend(
+                    self.cells(&row)
+                        .iter()
+                        .map(|value| quote(value, ',').into_owned()),
+                );
+                writeln!(self.out, "{}", fields.join(","))?;
+            }
+            Format::Table => {
+                let mut cells = vec![file.to_owned(), row.number.to_string()];
+                //a multiline CSV field would break the table apart
+                cells.extend(
+                    self.cells(&row)
+                        .iter()
+                        .map(|value| value.replace(['\r', '\n'], " ")),
+                );
+                self.table.push(cells);
+            }
+        }
+        self.printed += 1;
+        Ok(())
+    }
+
+    fn count(&mut self, file: Option<&str>, count: usize) -> Result<(), InterpreterError> {
+        let name = match self.header.results {
+            Results::Counts(name) => name,
+            _ => unreachable!(),
+        };
+        if self.printed == 0 {
+            self.start(&[])?;
+        }
+        match (self.header.format, file) {
+            (Format::Text, Some(file)) if self.header.prefixed => {
+                writeln!(self.out, "{}:{}", file, count)?
+            }
+            (Format::Text, Some(_)) => writeln!(self.out, "{}", count)?,
+            (Format::Text, None) => writeln!(self.out, "total:{}", count)?,
+            (Format::Json | Format::Ndjson, file) => {
+                let file = file.map_or_else(|| "null".to_owned(), json_string);
+                self.json_object(&format!(r#"{{"file":{},"{}":{}}}"#, file, name, count))?;
+            }
+            (Format::Csv, file) => writeln!(
+                self.out,
+                "{},{}",
+                quote(file.unwrap_or_default(), ','),
+                count
+            )?,
+            (Format::Table, file) => self
+                .table
+                .push(vec![file.unwrap_or("total").to_owned(), count.to_string()]),
+        }
+        self.printed += 1;
+        Ok(())
+    }
+
+    // close the JSON array or print the TABLE, and make sure it has all been written
+    fn end(&mut self) -> Result<(), InterpreterError> {
+        match (self.header.format, self.header.results) {
+            (_, Results::Done) => {}
+            //the header of a CSV without a single row
+            (Format::Csv, _) if self.printed == 0 => self.start(&[])?,
+            (Format::Json, _) if self.printed == 0 => writeln!(self.out, "[]")?,
+            (Format::Json, _) => writeln!(self.out, "\n]")?,
+            (Format::Table, _) => self.print_table()?,
+            _ => {}
+        }
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<(), InterpreterError> {
+        self.out.flush()?;
+        Ok(())
+    }
+
+    // text only shows line numbers WITH LINENO, every other format always has them
+    fn needs_line_numbers(&self, header: &Header) -> bool {
+        header.numbered || header.format != Format::Text
+    }
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("a string always serializes")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::record::Fields;
+
+    // a Write that can still be looked at after the Printer that owns it is gone
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn print(format: Format, results: Results) -> String {
+        let out = Shared::default();
+        let mut printer = Printer::new(Box::new(out.clone()));
+        printer
+            .begin(&Header {
+                results,
+                format,
+                prefixed: true,
+                numbered: false,
+            })
+            .unwrap();
+        match results {
+            Results::Rows => {
+                printer
+                    .row("a.log", Row::new(3, "say \"hi\", ok".to_owned()))
+                    .unwrap();
+                printer.row("b.log", Row::new(12, "é".to_owned())).unwrap();
+            }
+            Results::Counts(_) => {
+                printer.count(Some("a.log"), 2).unwrap();
+                printer.count(Some("logs/b.log"), 10).unwrap();
+                printer.count(None, 12).unwrap();
+            }
+            Results::Done => {}
+        }
+        printer.end().unwrap();
+        let bytes = out.0.borrow().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn it_can_print_rows_in_every_format() {
+        assert_eq!(
+            print(Format::Text, Results::Rows),
+            "a.log:say \"hi\", ok\nb.log:é\n"
+        );
+        assert_eq!(
+            print(Format::Json, Results::Rows),
+            "[\n  {\"file\":\"a.log\",\"lineno\":3,\"line\":\"say \\\"hi\\\", ok\"},\n  {\"file\":\"b.log\",\"lineno\":12,\"line\":\"é\"}\n]\n"
+        );
+        assert_eq!(
+            print(Format::Ndjson, Results::Rows),
+            "{\"file\":\"a.log\",\"lineno\":3,\"line\":\"say \\\"hi\\\", ok\"}\n{\"file\":\"b.log\",\"lineno\":12,\"line\":\"é\"}\n"
+        );
+        assert_eq!(
+            print(Format::Csv, Results::Rows),
+            "file,lineno,line\na.log,3,\"say \"\"hi\"\", ok\"\nb.log,12,é\n"
+        );
+        assert_eq!(
+            print(Format::Table, Results::Rows),
+            "file   lineno  line\n-----  ------  ------------\na.log       3  say \"hi\", ok\nb.log      12  é\n"
+        );
+    }
+
+    #[test]
+    fn it_can_print_rows_with_columns() {
+        let print = |format: Format| {
+            let out = Shared::default();
+            let mut printer = Printer::new(Box::new(out.clone()));
+            printer
+                .begin(&Header {
+                    results: Results::Rows,
+                    format,
+                    prefixed: false,
+                    numbered: false,
+                })
+                .unwrap();
+            let names: Vec<String> = vec!["name".to_owned(), "amount".to_owned()];
+            for (number, name, amount) in [(2, "ann", "120"), (4, "two\nlines", "7")] {
+                let mut row = Row::new(number, format!("{},{}", name, amount));
+                row.fields = Fields::new(names.clone().into(), vec![name.into(), amount.into()]);
+                printer.row("d.csv", row).unwrap();
+            }
+            printer.end().unwrap();
+            let bytes = out.0.borrow().clone();
+            String::from_utf8(bytes).unwrap()
+        };
+
+        assert_eq!(
+            print(Format::Ndjson),
+            "{\"file\":\"d.csv\",\"lineno\":2,\"name\":\"ann\",\"amount\":\"120\"}\n{\"file\":\"d.csv\",\"lineno\":4,\"name\":\"two\\nlines\",\"amount\":\"7\"}\n"
+        );
+        assert_eq!(
+            print(Format::Csv),
+            "file,lineno,name,amount\nd.csv,2,ann,120\nd.csv,4,\"two\nlines\",7\n"
+        );
+        assert_eq!(
+            print(Format::Table),
+            "file   lineno  name       amount\n-----  ------  ---------  ------\nd.csv       2  ann           120\nd.csv       4  two lines       7\n"
+        );
+    }
+
+    #[test]
+    fn it_can_print_counts_in_every_format() {
+        assert_eq!(
+            print(Format::Text, Results::Counts("lines")),
+            "a.log:2\nlogs/b.log:10\ntotal:12\n"
+        );
+        assert_eq!(
+            print(Format::Ndjson, Results::Counts("lines")),
+            "{\"file\":\"a.log\",\"lines\":2}\n{\"file\":\"logs/b.log\",\"lines\":10}\n{\"file\":null,\"lines\":12}\n"
+        );
+        assert_eq!(
+            print(Format::Csv, Results::Counts("words")),
+            "file,words\na.log,2\nlogs/b.log,10\n,12\n"
+        );
+        assert_eq!(
+            print(Format::Table, Results::Counts("chars")),
+            "file        chars\n----------  -----\na.log           2\nlogs/b.log     10\ntotal          12\n"
+        );
+
+        let out = Shared::default();
+        let mut printer = Printer::new(Box::new(out.clone()));
+        printer
+            .begin(&Header {
+                results: Results::Rows,
+                format: Format::Json,
+                prefixed: false,
+                numbered: false,
+            })
+            .unwrap();
+        printer.end().unwrap();
+        assert_eq!(*out.0.borrow(), b"[]\n");
+    }
+}
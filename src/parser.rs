@@ -14,7 +14,6 @@ pub fn parse(tokens: Vec<Token>) -> Result<Program, ParseError> {
     while let Some(statement) = parser.next()? {
         program.push(statement);
     }
-    println!("We have {} QUERIES to run", program.len());
     Ok(program)
 }
 
@@ -27,22 +26,16 @@ pub struct Parser<'a> {
 impl<'a> Parser<'a> {
     fn new(tokens: Iter<'a, Token>) -> Self {
         Self {
-            tokens: tokens,
+            tokens,
             current: Token::Eof,
             peek: Token::Eof,
         }
     }
 
     fn next(&mut self) -> Result<Option<Statement>, ParseError> {
-        println!("Current before next {:?}", self.current);
         if self.current_is(Token::Eos) {
             self.read();
-            self.read();
         }
-        println!(
-            "After EOS self.read we now have a new token which is >>> {:?}",
-            self.current
-        );
         if self.current == Token::Eof {
             return Ok(None);
         }
@@ -51,83 +44,644 @@ impl<'a> Parser<'a> {
     fn parse_statement(&mut self) -> Result<Statement, ParseError> {
         match self.current {
             Token::Select => self.parse_select_statement(),
-            //Token::Create => self.parse_create_statement(),
+            Token::Create => self.parse_create_statement(),
+            Token::Update => self.parse_update_statement(),
+            Token::Replace => self.parse_replace_statement(),
+            Token::Delete => self.parse_delete_statement(),
             _ => Err(ParseError::UnexpectedToken(self.current.clone())),
         }
     }
 
-    // fn parse_create_statement(&mut self) -> Result<Statement, ParseError>{
+    fn parse_create_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect_token_and_read(Token::Create)?;
+
+        // CREATE IF NOT EXISTS "file.txt" is a no-op when the file is already there...
+        let if_not_exists = if self.current_is(Token::If) {
+            self.expect_token_and_read(Token::If)?;
+            self.expect_token_and_read(Token::Not)?;
+            self.expect_token_and_read(Token::Exists)?;
+            true
+        } else {
+            false
+        };
+
+        let target: String = self.expect_identifier_and_read()?.into();
+
+        // optional initial content, one STRING LITERAL per line...
+        let mut content: Vec<Identifier> = Vec::new();
+        if self.current_is(Token::With) {
+            self.expect_token_and_read(Token::With)?;
+            content.push(self.expect_identifier_and_read()?.into());
+            while self.current_is(Token::Strings("".to_owned())) {
+                content.push(self.expect_identifier_and_read()?.into());
+            }
+        }
+
+        Ok(Statement::CreateStatement {
+            target,
+            if_not_exists,
+            content,
+        })
+    }
+    fn parse_update_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect_token_and_read(Token::Update)?;
+
+        let target: String = self.expect_identifier_and_read()?.into();
+
+        self.expect_token_and_read(Token::Set)?;
+        let mut content: Vec<Identifier> = vec![self.expect_identifier_and_read()?.into()];
+        while self.current_is(Token::Strings("".to_owned())) {
+            content.push(self.expect_identifier_and_read()?.into());
+        }
+
+        let placement = if self.current_is(Token::At) {
+            self.expect_token_and_read(Token::At)?;
+            self.expect_token_and_read(Token::Line)?;
+            Some(Placement::AtLine(self.expect_line_number_and_read()?))
+        } else if self.current_is(Token::Before) {
+            self.expect_token_and_read(Token::Before)?;
+            Some(Placement::Before(self.expect_identifier_and_read()?.into()))
+        } else if self.current_is(Token::After) {
+            self.expect_token_and_read(Token::After)?;
+            Some(Placement::After(self.expect_identifier_and_read()?.into()))
+        } else {
+            None
+        };
+
+        Ok(Statement::UpdateStatement {
+            target,
+            content,
+            placement,
+        })
+    }
+
+    fn parse_replace_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect_token_and_read(Token::Replace)?;
+
+        let target: String = self.expect_identifier_and_read()?.into();
 
+        self.expect_token_and_read(Token::Regex)?;
+        let pattern: String = self.expect_identifier_and_read()?.into();
+        let replacement: String = self.expect_identifier_and_read()?.into();
+
+        let condition = self.parse_where_expression()?;
+
+        let limit = if self.current_is(Token::Limit) {
+            self.expect_token_and_read(Token::Limit)?;
+            Some(self.expect_count_and_read()?)
+        } else {
+            None
+        };
+
+        Ok(Statement::ReplaceStatement {
+            target,
+            pattern,
+            replacement,
+            condition,
+            limit,
+        })
+    }
+
+    fn parse_delete_statement(&mut self) -> Result<Statement, ParseError> {
+        self.expect_token_and_read(Token::Delete)?;
+
+        let quantity = if self.current_is(Token::Top("".to_owned())) {
+            Some(self.expect_token_and_read(Token::Top("".to_owned()))?)
+        } else if self.current_is(Token::Tail("".to_owned())) {
+            Some(self.expect_token_and_read(Token::Tail("".to_owned()))?)
+        } else {
+            None
+        };
+
+        self.expect_token_and_read(Token::From)?;
+        let target: String = self.expect_identifier_and_read()?.into();
+
+        //no DELETE without a WHERE, we never want to wipe a whole file by accident...
+        let condition = match self.parse_where_expression()? {
+            Some(condition) => condition,
+            None => return Err(ParseError::UnexpectedToken(self.current.clone())),
+        };
+
+        Ok(Statement::DeleteStatement {
+            quantity,
+            target,
+            condition,
+        })
+    }
 
-    //     Ok(())
-    // }
     fn parse_select_statement(&mut self) -> Result<Statement, ParseError> {
         self.expect_token_and_read(Token::Select)?;
 
-        let counters = if self.current_is(Token::CountC){
+        let counters = if self.current_is(Token::CountC) {
             let token = self.expect_token_and_read(Token::CountC)?;
             Some(token)
-        }else if self.current_is(Token::CountL){
+        } else if self.current_is(Token::CountL) {
             let token = self.expect_token_and_read(Token::CountL)?;
             Some(token)
-        }else if self.current_is(Token::CountW){
+        } else if self.current_is(Token::CountW) {
             let token = self.expect_token_and_read(Token::CountW)?;
             Some(token)
-        }else{
+        } else {
             None
         };
 
-        let quantity = if self.current_is(Token::Top("".to_owned())) {
+        // SELECT name, amount FROM CSV... is SELECT * with only those columns
+        let quantity = if counters.is_none() && self.current_is_column() {
+            Token::Star
+        } else if self.current_is(Token::Top("".to_owned())) {
             self.expect_token_and_read(Token::Top("".to_owned()))?
+        } else if self.current_is(Token::Lines("".to_owned())) {
+            self.expect_token_and_read(Token::Lines("".to_owned()))?
         } else if self.current_is(Token::Star) {
             self.expect_token_and_read(Token::Star)?
         } else {
             self.expect_token_and_read(Token::Tail("".to_owned()))?
         };
 
+        let columns = if counters.is_none() {
+            self.parse_columns()?
+        } else {
+            Vec::new()
+        };
+
+        // TAIL(20) FOLLOW keeps watching the file like tail -f, it only makes sense after TAIL...
+        let follow = matches!(quantity, Token::Tail(_)) && self.current_is(Token::Follow);
+        if follow {
+            self.expect_token_and_read(Token::Follow)?;
+            if counters.is_some() {
+                return Err(ParseError::InvalidFollow(
+                    "COUNTC, COUNTL or COUNTW".to_owned(),
+                ));
+            }
+        }
+
         let from = self.expect_token_and_read(Token::From)?;
 
-        let target: String = self.expect_identifier_and_read()?.into();
+        // FROM CSV "data.csv" DELIMITER ";" HEADER reads records with columns instead of lines,
+        // and so do FROM JSON "items.json" and FROM NDJSON "events.log"
+        let record = match self.current {
+            Token::Csv => Some(RecordFormat::Csv {
+                delimiter: ',',
+                header: false,
+            }),
+            Token::Tsv => Some(RecordFormat::Csv {
+                delimiter: '\t',
+                header: false,
+            }),
+            Token::Json => Some(RecordFormat::Json),
+            Token::Ndjson => Some(RecordFormat::Ndjson),
+            _ => None,
+        };
+        if record.is_some() {
+            self.read();
+        }
+
+        let source = self.parse_source()?;
+        let record = match record {
+            Some(RecordFormat::Csv { delimiter, .. }) => {
+                Some(self.parse_record_options(delimiter)?)
+            }
+            None if self.current_is(Token::Parse) => Some(self.parse_regex_format()?),
+            None if self.current_is(Token::Format) => Some(self.parse_preset()?),
+            record => record,
+        };
+        if follow && record.is_some() {
+            return Err(ParseError::InvalidFollow(
+                "FROM CSV, TSV, JSON, NDJSON, PARSE or FORMAT".to_owned(),
+            ));
+        }
+
+        // ENCODING "latin1" says how the bytes are turned into lines, UTF-8 by default
+        let encoding = if self.current_is(Token::Encoding) {
+            self.expect_token_and_read(Token::Encoding)?;
+            Some(self.expect_identifier_and_read()?.into())
+        } else {
+            None
+        };
 
         // Do we have Conditional Expression after TARGET "../filePath.txt"?
-        let condition_expression: Option<Expression> = if self.current_is(Token::Where) {
-            self.expect_token_and_read(Token::Where)?;
-            //after reading WHERE, next should be a conditional followed by a STRING LITERAL of the Search TERM...
-            if !self.current_is(Token::Like) || !self.current_is(Token::NotLike){
-                return Err(ParseError::UnexpectedToken(self.current.clone()))
-            }
-            let conditional = match self.current{
-                Token::Like => Token::Like,
-                Token::NotLike => Token::NotLike,
-                Token::Regex => Token::Regex,
-                _ => unreachable!()
-            };
-            //move to the next token...
-            // if self.peek != Token::Strings("".to_owned()){
-            //     return Err(ParseError::UnexpectedToken(self.current.clone()))
-            // }
-            let target_search_string: String  = match self.expect_identifier_and_read(){
-                Ok(s) => s.into(),
-                Err(e) => return Err(ParseError::UnexpectedToken(self.current.clone()))
-            };
+        let condition_expression: Option<Expression> = self.parse_where_expression()?;
 
-            Some(Expression::Where { conditional: Some(conditional), target: target_search_string })
+        // ORDER BY LENGTH DESC sorts what WHERE matched, before TOP or TAIL take from it
+        let order = if self.current_is(Token::Order) {
+            Some(self.parse_order_by()?)
+        } else {
+            None
+        };
+        if follow && order.is_some() {
+            return Err(ParseError::InvalidFollow("ORDER BY".to_owned()));
+        }
 
+        // LIMIT n OFFSET m pages through the result, SKIP is another name for OFFSET...
+        let mut limit = None;
+        let mut offset = None;
+        loop {
+            if limit.is_none() && self.current_is(Token::Limit) {
+                self.expect_token_and_read(Token::Limit)?;
+                limit = Some(self.expect_count_and_read()?);
+            } else if offset.is_none()
+                && (self.current_is(Token::Offset) || self.current_is(Token::Skip))
+            {
+                self.read();
+                offset = Some(self.expect_count_and_read()?);
+            } else {
+                break;
+            }
+        }
+
+        if follow && (limit.is_some() || offset.is_some()) {
+            return Err(ParseError::InvalidFollow("LIMIT or OFFSET".to_owned()));
+        }
+
+        // WITH LINENO prints where each line came from, "42:the line"
+        let numbered = if self.current_is(Token::With) {
+            self.expect_token_and_read(Token::With)?;
+            self.expect_token_and_read(Token::LineNo)?;
+            true
+        } else {
+            false
+        };
+
+        // OUTPUT JSON|NDJSON|CSV|TABLE|TEXT, how the results are written...
+        let output = if self.current_is(Token::Output) {
+            self.expect_token_and_read(Token::Output)?;
+            let format = match self.current {
+                Token::Text => Format::Text,
+                Token::Json => Format::Json,
+                Token::Ndjson => Format::Ndjson,
+                Token::Csv => Format::Csv,
+                Token::Table => Format::Table,
+                _ => return Err(ParseError::UnexpectedToken(self.current.clone())),
+            };
+            self.read();
+            Some(format)
+        } else {
+            None
+        };
+
+        // and where do the results go? > "out.txt" overwrites, >> "out.txt" appends...
+        let export = if self.current_is(Token::Export) {
+            self.expect_token_and_read(Token::Export)?;
+            Some(Export::Overwrite(self.expect_identifier_and_read()?.into()))
+        } else if self.current_is(Token::ExportAppend) {
+            self.expect_token_and_read(Token::ExportAppend)?;
+            Some(Export::Append(self.expect_identifier_and_read()?.into()))
         } else {
             None
         };
 
         Ok(Statement::SelectStatement {
-            counters: counters,
+            counters,
             quantity: Some(quantity),
-            from: from,
-            target: target,
+            columns,
+            follow,
+            from,
+            record,
+            source,
+            encoding,
             condition: condition_expression,
+            order,
+            limit,
+            offset,
+            numbered,
+            output,
+            export,
+        })
+    }
+    // name, amount, the columns a SELECT keeps, or paths like $.user.name
+    fn parse_columns(&mut self) -> Result<Vec<Identifier>, ParseError> {
+        let mut columns = Vec::new();
+        if !self.current_is_column() {
+            return Ok(columns);
+        }
+        loop {
+            if !self.current_is_column() {
+                return Err(ParseError::UnexpectedToken(self.current.clone()));
+            }
+            columns.push(self.current.clone().into());
+            self.read();
+            if !self.current_is(Token::Comma) {
+                return Ok(columns);
+            }
+            self.read();
+        }
+    }
+
+    // DELIMITER ";" and HEADER, in any order
+    fn parse_record_options(&mut self, mut delimiter: char) -> Result<RecordFormat, ParseError> {
+        let mut header = false;
+        loop {
+            match self.current {
+                Token::Delimiter => {
+                    self.read();
+                    let given: String = self.expect_identifier_and_read()?.into();
+                    //strings are taken literally, so a tab has to be spelled out
+                    delimiter = match given.as_str() {
+                        "\\t" => '\t',
+                        _ => {
+                            let mut chars = given.chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(c), None)
+                                    if c.is_ascii() && !matches!(c, '"' | '\n' | '\r') =>
+                                {
+                                    c
+                                }
+                                _ => return Err(ParseError::InvalidDelimiter(given)),
+                            }
+                        }
+                    };
+                }
+                Token::Header => {
+                    self.read();
+                    header = true;
+                }
+                _ => return Ok(RecordFormat::Csv { delimiter, header }),
+            }
+        }
+    }
+
+    // ORDER BY LINE|LENGTH|<column> [ASC|DESC] [NUMERIC|NATURAL]
+    fn parse_order_by(&mut self) -> Result<OrderBy, ParseError> {
+        self.expect_token_and_read(Token::Order)?;
+        self.expect_token_and_read(Token::By)?;
+        let key = match &self.current {
+            Token::Line => SortKey::Line,
+            Token::Length => SortKey::Length,
+            Token::Identifier(name) | Token::Path(name) => SortKey::Column(name.clone()),
+            _ => return Err(ParseError::UnexpectedToken(self.current.clone())),
+        };
+        self.read();
+        let descending = match self.current {
+            Token::Asc | Token::Desc => {
+                let descending = self.current_is(Token::Desc);
+                self.read();
+                descending
+            }
+            _ => false,
+        };
+        let collation = match self.current {
+            Token::Numeric => Collation::Numeric,
+            Token::Natural => Collation::Natural,
+            _ => {
+                return Ok(OrderBy {
+                    key,
+                    descending,
+                    collation: Collation::Default,
+                })
+            }
+        };
+        self.read();
+        Ok(OrderBy {
+            key,
+            descending,
+            collation,
+        })
+    }
+
+    // PARSE REGEX "(?P<ip>\S+) ..." KEEP UNPARSED, the lines split up by the named groups
+    fn parse_regex_format(&mut self) -> Result<RecordFormat, ParseError> {
+        self.expect_token_and_read(Token::Parse)?;
+        self.expect_token_and_read(Token::Regex)?;
+        let pattern = self.expect_identifier_and_read()?.into();
+        let unparsed = self.parse_keep_unparsed()?;
+        Ok(RecordFormat::Regex { pattern, unparsed })
+    }
+
+    // FORMAT nginx_combined KEEP UNPARSED, the name of a preset with or without quotes
+    fn parse_preset(&mut self) -> Result<RecordFormat, ParseError> {
+        self.expect_token_and_read(Token::Format)?;
+        let name = match &self.current {
+            Token::Identifier(name) | Token::Strings(name) => name.clone(),
+            _ => return Err(ParseError::UnexpectedToken(self.current.clone())),
+        };
+        self.read();
+        let unparsed = self.parse_keep_unparsed()?;
+        Ok(RecordFormat::Preset { name, unparsed })
+    }
+
+    fn parse_keep_unparsed(&mut self) -> Result<bool, ParseError> {
+        if !self.current_is(Token::Keep) {
+            return Ok(false);
+        }
+        self.read();
+        self.expect_token_and_read(Token::Unparsed)?;
+        Ok(true)
+    }
+
+    // "file.txt" (or a glob), STDIN (or "-"), or DIR "path" followed by its walking options in any order
+    fn parse_source(&mut self) -> Result<Source, ParseError> {
+        if self.current_is(Token::Stdin) {
+            self.expect_token_and_read(Token::Stdin)?;
+            return Ok(Source::Stdin);
+        }
+        if !self.current_is(Token::Dir) {
+            let target: String = self.expect_identifier_and_read()?.into();
+            if target == "-" {
+                return Ok(Source::Stdin);
+            }
+            return Ok(Source::Path(target));
+        }
+        self.expect_token_and_read(Token::Dir)?;
+
+        let mut dir = DirSource {
+            path: self.expect_identifier_and_read()?.into(),
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            hidden: false,
+            follow_links: false,
+        };
+        loop {
+            match self.current {
+                Token::Recursive => {
+                    self.read();
+                    dir.recursive = true;
+                }
+                Token::Include => {
+                    self.read();
+                    dir.include.push(self.expect_identifier_and_read()?.into());
+                    while self.current_is(Token::Strings("".to_owned())) {
+                        dir.include.push(self.expect_identifier_and_read()?.into());
+                    }
+                }
+                Token::Exclude => {
+                    self.read();
+                    dir.exclude.push(self.expect_identifier_and_read()?.into());
+                    while self.current_is(Token::Strings("".to_owned())) {
+                        dir.exclude.push(self.expect_identifier_and_read()?.into());
+                    }
+                }
+                Token::Hidden => {
+                    self.read();
+                    dir.hidden = true;
+                }
+                Token::Follow => {
+                    self.read();
+                    self.expect_token_and_read(Token::Symlinks)?;
+                    dir.follow_links = true;
+                }
+                _ => break,
+            }
+        }
+        Ok(Source::Dir(dir))
+    }
+
+    fn parse_where_expression(&mut self) -> Result<Option<Expression>, ParseError> {
+        if !self.current_is(Token::Where) {
+            return Ok(None);
+        }
+        self.expect_token_and_read(Token::Where)?;
+
+        Ok(Some(self.parse_or_expression()?))
+    }
+
+    // precedence from loosest to tightest: OR, AND, NOT, then (groups) and single predicates
+    fn parse_or_expression(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_and_expression()?;
+        while self.current_is(Token::Or) {
+            self.expect_token_and_read(Token::Or)?;
+            let right = self.parse_and_expression()?;
+            left = Expression::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and_expression(&mut self) -> Result<Expression, ParseError> {
+        let mut left = self.parse_not_expression()?;
+        while self.current_is(Token::And) {
+            self.expect_token_and_read(Token::And)?;
+            let right = self.parse_not_expression()?;
+            left = Expression::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not_expression(&mut self) -> Result<Expression, ParseError> {
+        if self.current_is(Token::Not) {
+            self.expect_token_and_read(Token::Not)?;
+            return Ok(Expression::Not(Box::new(self.parse_not_expression()?)));
+        }
+        if self.current_is(Token::LeftParen) {
+            self.expect_token_and_read(Token::LeftParen)?;
+            let expression = self.parse_or_expression()?;
+            self.expect_token_and_read(Token::RightParen)?;
+            return Ok(expression);
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expression, ParseError> {
+        //a conditional followed by a STRING LITERAL of the Search TERM...
+        let conditional = match self.current {
+            Token::Like | Token::NotLike | Token::ILike | Token::NotILike | Token::Regex => {
+                self.current.clone()
+            }
+            _ => return self.parse_comparison(),
+        };
+        self.read();
+        let target_search_string: String = self.expect_identifier_and_read()?.into();
+
+        // LIKE "50!%" ESCAPE "!", the escape has to be exactly one character...
+        let escape = if self.current_is(Token::Escape) && conditional != Token::Regex {
+            self.expect_token_and_read(Token::Escape)?;
+            let escape: String = self.expect_identifier_and_read()?.into();
+            let mut chars = escape.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => return Err(ParseError::InvalidEscape(escape)),
+            }
+        } else {
+            None
+        };
+
+        Ok(Expression::Predicate {
+            conditional,
+            target: target_search_string,
+            escape,
         })
     }
+
+    // LINE = "exact", LINE != "exact", LINE >= 100...
+    fn parse_comparison(&mut self) -> Result<Expression, ParseError> {
+        let left = self.parse_operand()?;
+
+        // LINENO BETWEEN 100 AND 200, this AND belongs to the BETWEEN and not to the WHERE
+        if self.current_is(Token::Between) {
+            self.expect_token_and_read(Token::Between)?;
+            let low = self.parse_operand()?;
+            self.expect_token_and_read(Token::And)?;
+            let high = self.parse_operand()?;
+            return Ok(Expression::Between {
+                operand: left,
+                low,
+                high,
+            });
+        }
+
+        let op = match self.current {
+            Token::Assign
+            | Token::NotEquals
+            | Token::Less
+            | Token::LessEquals
+            | Token::Export
+            | Token::GreaterEquals => Op::token(self.current.clone()),
+            _ => return Err(ParseError::UnexpectedToken(self.current.clone())),
+        };
+        self.read();
+        let right = self.parse_operand()?;
+
+        Ok(Expression::Comparison { left, op, right })
+    }
+
+    // + and - bind looser than * / and %, as usual
+    fn parse_operand(&mut self) -> Result<Operand, ParseError> {
+        let mut left = self.parse_term()?;
+        while matches!(self.current, Token::Plus | Token::Minus) {
+            let op = self.current.clone();
+            self.read();
+            let right = self.parse_term()?;
+            left = Operand::Arithmetic {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_term(&mut self) -> Result<Operand, ParseError> {
+        let mut left = self.parse_value()?;
+        while matches!(self.current, Token::Star | Token::Slash | Token::Percent) {
+            let op = self.current.clone();
+            self.read();
+            let right = self.parse_value()?;
+            left = Operand::Arithmetic {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_value(&mut self) -> Result<Operand, ParseError> {
+        let operand = match &self.current {
+            Token::Line => Operand::Line,
+            Token::LineNo => Operand::LineNo,
+            Token::Identifier(name) | Token::Path(name) => Operand::Column(name.clone()),
+            Token::Strings(s) => Operand::Strings(s.clone()),
+            Token::Number(n) => Operand::Number(*n),
+            _ => return Err(ParseError::UnexpectedToken(self.current.clone())),
+        };
+        self.read();
+        Ok(operand)
+    }
+
+    fn current_is_column(&self) -> bool {
+        matches!(self.current, Token::Identifier(_) | Token::Path(_))
+    }
+
     fn expect_token(&mut self, token: Token) -> Result<Token, ParseError> {
-        // let c = self.current.clone();
-        // println!("current Token: {:?}", c);
         if self.current_is(token) {
             Ok(self.current.clone())
         } else {
@@ -137,9 +691,6 @@ impl<'a> Parser<'a> {
 
     fn expect_token_and_read(&mut self, token: Token) -> Result<Token, ParseError> {
         let result = self.expect_token(token)?;
-
-        println!("Result Token: {:?}", result);
-
         self.read();
 
         Ok(result)
@@ -148,6 +699,27 @@ impl<'a> Parser<'a> {
     fn expect_identifier_and_read(&mut self) -> Result<Token, ParseError> {
         self.expect_token_and_read(Token::Strings("".to_string()))
     }
+    // line numbers are 1-based whole numbers...
+    fn expect_line_number_and_read(&mut self) -> Result<usize, ParseError> {
+        match self.current {
+            Token::Number(n) if n >= 1.0 && n.fract() == 0.0 => {
+                self.read();
+                Ok(n as usize)
+            }
+            _ => Err(ParseError::UnexpectedToken(self.current.clone())),
+        }
+    }
+
+    fn expect_count_and_read(&mut self) -> Result<usize, ParseError> {
+        match self.current {
+            Token::Number(n) if n >= 0.0 && n.fract() == 0.0 => {
+                self.read();
+                Ok(n as usize)
+            }
+            _ => Err(ParseError::UnexpectedToken(self.current.clone())),
+        }
+    }
+
     fn current_is(&self, token: Token) -> bool {
         std::mem::discriminant(&self.current) == std::mem::discriminant(&token)
     }
@@ -162,8 +734,560 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// What is wrong with the text of a script.
 #[derive(Debug, Error)]
 pub enum ParseError {
     #[error("Unexpected token {0:?}.")]
     UnexpectedToken(Token),
+    #[error("ESCAPE must be a single character, got {0:?}.")]
+    InvalidEscape(String),
+    #[error("FOLLOW cannot be combined with {0}.")]
+    InvalidFollow(String),
+    #[error("DELIMITER must be a single ASCII character or \"\\t\", got {0:?}.")]
+    InvalidDelimiter(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token;
+
+    #[test]
+    fn it_can_parse_create_statements() {
+        let tokens = token::generate(
+            r#"CREATE "a.txt"; CREATE IF NOT EXISTS "logs/b.txt" WITH "line1" "line2";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert_eq!(
+            program,
+            vec![
+                Statement::CreateStatement {
+                    target: "a.txt".to_owned(),
+                    if_not_exists: false,
+                    content: vec![],
+                },
+                Statement::CreateStatement {
+                    target: "logs/b.txt".to_owned(),
+                    if_not_exists: true,
+                    content: vec!["line1".to_owned(), "line2".to_owned()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_update_statements() {
+        let tokens = token::generate(
+            r#"UPDATE "a.ini" SET "x=1"; UPDATE "a.ini" SET "y=2" "z=3" AT LINE 4; UPDATE "a.ini" SET "[extra]" BEFORE "[tail]";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert_eq!(
+            program,
+            vec![
+                Statement::UpdateStatement {
+                    target: "a.ini".to_owned(),
+                    content: vec!["x=1".to_owned()],
+                    placement: None,
+                },
+                Statement::UpdateStatement {
+                    target: "a.ini".to_owned(),
+                    content: vec!["y=2".to_owned(), "z=3".to_owned()],
+                    placement: Some(Placement::AtLine(4)),
+                },
+                Statement::UpdateStatement {
+                    target: "a.ini".to_owned(),
+                    content: vec!["[extra]".to_owned()],
+                    placement: Some(Placement::Before("[tail]".to_owned())),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_replace_statements() {
+        let tokens = token::generate(
+            r#"REPLACE "a.log" REGEX "user=(\w+)" "user=${1}_old" WHERE LIKE "login" LIMIT 2;"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert_eq!(
+            program,
+            vec![Statement::ReplaceStatement {
+                target: "a.log".to_owned(),
+                pattern: r"user=(\w+)".to_owned(),
+                replacement: "user=${1}_old".to_owned(),
+                condition: Some(Expression::Predicate {
+                    conditional: Token::Like,
+                    target: "login".to_owned(),
+                    escape: None,
+                }),
+                limit: Some(2),
+            }]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_select_with_where_conditions() {
+        let tokens = token::generate(
+            r#"SELECT * FROM "a.log" WHERE NOTLIKE "debug"; SELECT TOP(5) FROM "a.log" WHERE REGEX "\d+";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert_eq!(program.len(), 2);
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement { condition: Some(Expression::Predicate { conditional: Token::Regex, target, .. }), .. } if target == r"\d+"
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_delete_statements() {
+        let tokens = token::generate(r#"DELETE TAIL(2) FROM "a.log" WHERE LIKE "debug";"#);
+        let program = parse(tokens).unwrap();
+
+        assert_eq!(
+            program,
+            vec![Statement::DeleteStatement {
+                quantity: Some(Token::Tail("TAIL(2)".to_owned())),
+                target: "a.log".to_owned(),
+                condition: Expression::Predicate {
+                    conditional: Token::Like,
+                    target: "debug".to_owned(),
+                    escape: None,
+                },
+            }]
+        );
+        assert!(parse(token::generate(r#"DELETE FROM "a.log";"#)).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_boolean_where_with_precedence() {
+        let tokens = token::generate(
+            r#"SELECT * FROM "a.log" WHERE LIKE "a" OR LIKE "b" AND NOT (LIKE "c" OR REGEX "d");"#,
+        );
+        let program = parse(tokens).unwrap();
+        let like = |target: &str, conditional: Token| {
+            Box::new(Expression::Predicate {
+                conditional,
+                target: target.to_owned(),
+                escape: None,
+            })
+        };
+
+        let expected = Expression::Or(
+            like("a", Token::Like),
+            Box::new(Expression::And(
+                like("b", Token::Like),
+                Box::new(Expression::Not(Box::new(Expression::Or(
+                    like("c", Token::Like),
+                    like("d", Token::Regex),
+                )))),
+            )),
+        );
+        assert!(
+            matches!(&program[0], Statement::SelectStatement { condition: Some(condition), .. } if *condition == expected)
+        );
+        assert!(parse(token::generate(
+            r#"SELECT * FROM "a.log" WHERE (LIKE "a" OR LIKE "b";"#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn it_can_parse_like_with_escape() {
+        let tokens = token::generate(r#"SELECT * FROM "a.log" WHERE ILIKE "50!%%" ESCAPE "!";"#);
+        let program = parse(tokens).unwrap();
+
+        let expected = Expression::Predicate {
+            conditional: Token::ILike,
+            target: "50!%%".to_owned(),
+            escape: Some('!'),
+        };
+        assert!(
+            matches!(&program[0], Statement::SelectStatement { condition: Some(condition), .. } if *condition == expected)
+        );
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT * FROM "a.log" WHERE LIKE "x" ESCAPE "!!";"#
+            )),
+            Err(ParseError::InvalidEscape(_))
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_comparisons_before_export() {
+        let tokens = token::generate(
+            r#"SELECT * FROM "a.log" WHERE LINE = "exact" OR LINE > 10 > "out.txt";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        let expected = Expression::Or(
+            Box::new(Expression::Comparison {
+                left: Operand::Line,
+                op: Op::Equals,
+                right: Operand::Strings("exact".to_owned()),
+            }),
+            Box::new(Expression::Comparison {
+                left: Operand::Line,
+                op: Op::Greater,
+                right: Operand::Number(10.0),
+            }),
+        );
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement { condition: Some(condition), export: Some(Export::Overwrite(_)), .. } if *condition == expected
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_line_numbers_and_ranges() {
+        let tokens = token::generate(
+            r#"SELECT LINES(10..20) FROM "a.log" WHERE LINENO BETWEEN 1 AND 5 AND LINENO % 10 = 0 WITH LINENO;"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        let expected = Expression::And(
+            Box::new(Expression::Between {
+                operand: Operand::LineNo,
+                low: Operand::Number(1.0),
+                high: Operand::Number(5.0),
+            }),
+            Box::new(Expression::Comparison {
+                left: Operand::Arithmetic {
+                    left: Box::new(Operand::LineNo),
+                    op: Token::Percent,
+                    right: Box::new(Operand::Number(10.0)),
+                },
+                op: Op::Equals,
+                right: Operand::Number(0.0),
+            }),
+        );
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement { quantity: Some(Token::Lines(_)), condition: Some(condition), numbered: true, .. } if *condition == expected
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_limit_offset_and_skip() {
+        let tokens = token::generate(
+            r#"SELECT * FROM "a.log" WHERE LIKE "x" LIMIT 10 OFFSET 20; SELECT * FROM "a.log" SKIP 5; SELECT * FROM "a.log" OFFSET 1 LIMIT 2;"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        let pages: Vec<(Option<usize>, Option<usize>)> = program
+            .iter()
+            .map(|statement| match statement {
+                Statement::SelectStatement { limit, offset, .. } => (*limit, *offset),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            pages,
+            vec![(Some(10), Some(20)), (None, Some(5)), (Some(2), Some(1))]
+        );
+        assert!(parse(token::generate(
+            r#"SELECT * FROM "a.log" LIMIT 10 LIMIT 2;"#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn it_can_parse_dir_sources() {
+        let tokens = token::generate(
+            r#"SELECT * FROM DIR "src" RECURSIVE INCLUDE "*.rs" "*.toml" EXCLUDE "target/**" FOLLOW SYMLINKS WHERE LIKE "todo"; SELECT * FROM "logs/*.log";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        let expected = Source::Dir(DirSource {
+            path: "src".to_owned(),
+            recursive: true,
+            include: vec!["*.rs".to_owned(), "*.toml".to_owned()],
+            exclude: vec!["target/**".to_owned()],
+            hidden: false,
+            follow_links: true,
+        });
+        assert!(
+            matches!(&program[0], Statement::SelectStatement { source, condition: Some(_), .. } if *source == expected)
+        );
+        assert!(
+            matches!(&program[1], Statement::SelectStatement { source: Source::Path(path), .. } if path == "logs/*.log")
+        );
+    }
+
+    #[test]
+    fn it_can_parse_stdin_sources() {
+        let tokens =
+            token::generate(r#"SELECT * FROM STDIN WHERE LIKE "panic"; SELECT COUNTL * FROM "-";"#);
+        let program = parse(tokens).unwrap();
+
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement {
+                source: Source::Stdin,
+                condition: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement {
+                source: Source::Stdin,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_tail_follow() {
+        let tokens = token::generate(
+            r#"SELECT TAIL(20) FOLLOW FROM "app.log" WHERE LIKE "ERROR" WITH LINENO; SELECT TAIL(5) FROM "app.log";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement {
+                follow: true,
+                condition: Some(_),
+                numbered: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement { follow: false, .. }
+        ));
+        assert!(parse(token::generate(r#"SELECT TOP(5) FOLLOW FROM "app.log";"#)).is_err());
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT COUNTL TAIL(5) FOLLOW FROM "app.log";"#
+            )),
+            Err(ParseError::InvalidFollow(_))
+        ));
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT TAIL(5) FOLLOW FROM "app.log" LIMIT 2;"#
+            )),
+            Err(ParseError::InvalidFollow(_))
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_output_formats() {
+        let tokens = token::generate(
+            r#"SELECT * FROM "a.log" WITH LINENO OUTPUT JSON > "a.json"; SELECT COUNTL * FROM "*.log" OUTPUT table; SELECT * FROM "a.log";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        let outputs: Vec<Option<Format>> = program
+            .iter()
+            .map(|statement| match statement {
+                Statement::SelectStatement { output, .. } => *output,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(outputs, vec![Some(Format::Json), Some(Format::Table), None]);
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement {
+                export: Some(_),
+                numbered: true,
+                ..
+            }
+        ));
+        assert!(parse(token::generate(r#"SELECT * FROM "a.log" OUTPUT "xml";"#)).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_csv_columns() {
+        let tokens = token::generate(
+            r#"SELECT name, amount FROM CSV "data.csv" HEADER WHERE amount > 100; SELECT TOP(5) c2 FROM TSV STDIN; SELECT * FROM CSV "x.csv" HEADER DELIMITER "\t";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement {
+                quantity: Some(Token::Star),
+                columns,
+                record: Some(RecordFormat::Csv { delimiter: ',', header: true }),
+                condition: Some(Expression::Comparison { left: Operand::Column(column), .. }),
+                ..
+            } if columns == &["name", "amount"] && column == "amount"
+        ));
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement {
+                quantity: Some(Token::Top(_)),
+                columns,
+                record: Some(RecordFormat::Csv { delimiter: '\t', header: false }),
+                source: Source::Stdin,
+                ..
+            } if columns == &["c2"]
+        ));
+        assert!(matches!(
+            &program[2],
+            Statement::SelectStatement { columns, record: Some(RecordFormat::Csv { delimiter: '\t', header: true }), .. } if columns.is_empty()
+        ));
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT * FROM CSV "a.csv" DELIMITER "::";"#
+            )),
+            Err(ParseError::InvalidDelimiter(_))
+        ));
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT TAIL(5) FOLLOW FROM CSV "a.csv";"#
+            )),
+            Err(ParseError::InvalidFollow(_))
+        ));
+        assert!(parse(token::generate(r#"SELECT name, FROM CSV "a.csv";"#)).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_json_paths() {
+        let tokens = token::generate(
+            r#"SELECT $.user.name, level FROM NDJSON "events.log" WHERE $.status >= 500; SELECT COUNTL * FROM JSON "items.json";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement {
+                columns,
+                record: Some(RecordFormat::Ndjson),
+                condition: Some(Expression::Comparison { left: Operand::Column(column), .. }),
+                ..
+            } if columns == &["$.user.name", "level"] && column == "$.status"
+        ));
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement {
+                record: Some(RecordFormat::Json),
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT TAIL(5) FOLLOW FROM NDJSON "events.log";"#
+            )),
+            Err(ParseError::InvalidFollow(_))
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_order_by() {
+        let tokens = token::generate(
+            r#"SELECT TOP(10) FROM "a.log" WHERE LIKE "x" ORDER BY LENGTH DESC LIMIT 5; SELECT name FROM CSV "d.csv" HEADER ORDER BY amount NUMERIC; SELECT * FROM "files.txt" ORDER BY LINE ASC NATURAL;"#,
+        );
+        let orders: Vec<Option<OrderBy>> = parse(tokens)
+            .unwrap()
+            .into_iter()
+            .map(|statement| match statement {
+                Statement::SelectStatement { order, .. } => order,
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(
+            orders,
+            vec![
+                Some(OrderBy {
+                    key: SortKey::Length,
+                    descending: true,
+                    collation: Collation::Default
+                }),
+                Some(OrderBy {
+                    key: SortKey::Column("amount".to_owned()),
+                    descending: false,
+                    collation: Collation::Numeric
+                }),
+                Some(OrderBy {
+                    key: SortKey::Line,
+                    descending: false,
+                    collation: Collation::Natural
+                }),
+            ]
+        );
+        assert!(matches!(
+            parse(token::generate(
+                r#"SELECT TAIL(5) FOLLOW FROM "a.log" ORDER BY LENGTH;"#
+            )),
+            Err(ParseError::InvalidFollow(_))
+        ));
+        assert!(parse(token::generate(r#"SELECT * FROM "a.log" ORDER LENGTH;"#)).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_regex_columns() {
+        let tokens = token::generate(
+            r#"SELECT ip, _unparsed FROM "access.log" PARSE REGEX "(?P<ip>\S+) " KEEP UNPARSED WHERE ip = "::1"; SELECT * FROM "logs/*.log" PARSE REGEX "(?P<level>[A-Z]+)";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement { columns, record: Some(RecordFormat::Regex { pattern, unparsed: true }), condition: Some(_), .. }
+                if columns == &["ip", "_unparsed"] && pattern == r"(?P<ip>\S+) "
+        ));
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement {
+                record: Some(RecordFormat::Regex {
+                    unparsed: false,
+                    ..
+                }),
+                ..
+            }
+        ));
+        assert!(parse(token::generate(
+            r#"SELECT * FROM "a.log" PARSE "(?P<a>.)";"#
+        ))
+        .is_err());
+        assert!(matches!(
+            &parse(token::generate(r#"SELECT status FROM "access.log" FORMAT nginx_combined KEEP UNPARSED;"#)).unwrap()[0],
+            Statement::SelectStatement { record: Some(RecordFormat::Preset { name, unparsed: true }), .. } if name == "nginx_combined"
+        ));
+    }
+
+    #[test]
+    fn it_can_parse_encodings() {
+        let tokens = token::generate(
+            r#"SELECT COUNTL * FROM "legacy.log" ENCODING "latin1" WHERE LIKE "é"; SELECT * FROM DIR "logs" ENCODING "utf-8-lossy";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert!(
+            matches!(&program[0], Statement::SelectStatement { encoding: Some(label), condition: Some(_), .. } if label == "latin1")
+        );
+        assert!(
+            matches!(&program[1], Statement::SelectStatement { source: Source::Dir(_), encoding: Some(label), .. } if label == "utf-8-lossy")
+        );
+    }
+
+    #[test]
+    fn it_can_parse_select_with_export() {
+        let tokens = token::generate(
+            r#"SELECT TOP(1000) FROM "in.json" > "out.json"; SELECT COUNTL * FROM "in.json" WHERE LIKE "id" >> "out.json";"#,
+        );
+        let program = parse(tokens).unwrap();
+
+        assert!(matches!(
+            &program[0],
+            Statement::SelectStatement { export: Some(Export::Overwrite(target)), .. } if target == "out.json"
+        ));
+        assert!(matches!(
+            &program[1],
+            Statement::SelectStatement { export: Some(Export::Append(target)), condition: Some(_), .. } if target == "out.json"
+        ));
+    }
+
+    #[test]
+    fn it_rejects_update_at_line_zero() {
+        let tokens = token::generate(r#"UPDATE "a.ini" SET "x=1" AT LINE 0;"#);
+        assert!(parse(tokens).is_err());
+    }
 }
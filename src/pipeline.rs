@@ -0,0 +1,540 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+};
+
+use crate::{
+    ast::OrderBy,
+    condition::Condition,
+    encoding::{self, Encoding},
+    interpreter::{InterpreterError, Row},
+    order,
+    record::Records,
+    source::Input,
+    token::Token,
+};
+
+// how much of a file TAIL reads at a time when it works backwards from the end
+const TAIL_BLOCK_SIZE: u64 = 64 * 1024;
+
+// Which of the matching rows a SELECT keeps, before LIMIT/OFFSET.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    All,
+    Top(usize),
+    Tail(usize),
+    // inclusive line numbers, LINES(100..200)
+    Lines(usize, usize),
+}
+
+// A SELECT runs as a pipeline over one input at a time:
+//
+//   scan (number every line) -> filter (LINES, WHERE) -> quantity (TOP/TAIL) -> page (OFFSET/LIMIT) -> project -> sink
+//
+// TOP and TAIL are per input, OFFSET/LIMIT is one page over the rows of all of them together.
+//
+// Nothing is collected on the way. A line is read, pushed through, and dropped, so the memory
+// used does not grow with the size of the file. The only thing kept around is the ring buffer
+// for TAIL(n), which never holds more than n rows. ORDER BY is the exception: it filters every
+// input first and sorts all of their matching rows together, and only then do TOP/TAIL and the
+// page pick from them.
+#[derive(Clone, Copy)]
+pub struct Pipeline<'a> {
+    pub condition: Option<&'a Condition>,
+    pub quantity: Quantity,
+    pub offset: usize,
+    pub limit: Option<usize>,
+    // whether the rows need their real line number, which TAIL only knows by reading from the start
+    pub numbered: bool,
+    pub encoding: Encoding,
+    // FROM CSV or JSON, records with columns instead of lines
+    pub records: Option<&'a Records>,
+    pub order: Option<&'a OrderBy>,
+}
+
+impl Pipeline<'_> {
+    // Run the pipeline over every input in turn, with one OFFSET/LIMIT page over all of them.
+    // `sink` gets every row with the index of the input it is from.
+    pub fn run_inputs<F>(&self, inputs: &[Input], mut sink: F) -> Result<(), InterpreterError>
+    where
+        F: FnMut(usize, Row) -> Result<(), InterpreterError>,
+    {
+        let mut page = self.page();
+        let Some(order) = self.order else {
+            for (i, input) in inputs.iter().enumerate() {
+                //a full page needs no more files opened
+                if page.is_full() {
+                    break;
+                }
+                self.run_input(input, &mut page, |row| sink(i, self.project(row)))?;
+            }
+            return Ok(());
+        };
+
+        //only the filter per input, LINES included, TOP/TAIL and the page come after the sort
+        let filter = Pipeline {
+            quantity: match self.quantity {
+                Quantity::Lines(..) => self.quantity,
+                _ => Quantity::All,
+            },
+            offset: 0,
+            limit: None,
+            order: None,
+            ..*self
+        };
+        let mut matching = Vec::new();
+        for (i, input) in inputs.iter().enumerate() {
+            filter.run_input(input, &mut filter.page(), |row| {
+                matching.push((i, row));
+                Ok(())
+            })?;
+        }
+        let sorted = order::sort(matching, order, |(_, row)| row);
+        self.select(sorted.into_iter().map(Ok), &mut page, |(i, row)| {
+            sink(i, self.project(row))
+        })
+    }
+
+    pub fn page(&self) -> Page {
+        Page::new(self.offset, self.limit)
+    }
+
+    // Run the pipeline over one input. Regular files go through `run_file`, everything else
+    // (stdin, pipes, ...) is scanned from the start.
+    pub fn run_input<F>(
+        &self,
+        input: &Input,
+        page: &mut Page,
+        sink: F,
+    ) -> Result<(), InterpreterError>
+    where
+        F: FnMut(Row) -> Result<(), InterpreterError>,
+    {
+        if let Some(path) = input.path() {
+            let file = File::open(path).map_err(|_| {
+                InterpreterError::InvalidOrUnavailableFile(path.display().to_string())
+            })?;
+            let metadata = file.metadata()?;
+            if metadata.is_file() {
+                return self.run_file(&file, metadata.len(), &input.to_string(), page, sink);
+            }
+        }
+        let name = input.to_string();
+        self.run(
+            BufReader::new(self.encoding.decode(input.open()?, name.clone())),
+            &name,
+            page,
+            sink,
+        )
+    }
+
+    // Run the pipeline over the first `len` bytes of a regular file, anything appended while it
+    // runs is left for later. TAIL(n) without a WHERE does not scan at all: it reads backwards
+    // from the end, so the last lines of a huge log come back right away.
+    pub fn run_file<F>(
+        &self,
+        mut file: &File,
+        len: u64,
+        name: &str,
+        page: &mut Page,
+        mut sink: F,
+    ) -> Result<(), InterpreterError>
+    where
+        F: FnMut(Row) -> Result<(), InterpreterError>,
+    {
+        let encoding = self.encoding.for_file(file)?;
+        match (self.quantity, self.condition, self.numbered, self.records) {
+            //going backwards needs newlines that can be found without decoding, so no UTF-16, and
+            //a CSV record can go over many lines, so no CSV either
+            (Quantity::Tail(n), None, false, None) if encoding.is_ascii_compatible() => {
+                for (offset, bytes) in read_tail(file, len, n)? {
+                    if page.is_full() {
+                        break;
+                    }
+                    let mut decoder = match offset {
+                        0 => encoding.decoder(name.to_owned()),
+                        _ => encoding.decoder_at(name.to_owned(), offset),
+                    };
+                    let mut line = String::new();
+                    decoder.decode(&bytes, true, &mut line)?;
+                    //the line number is never looked at, see `numbered`
+                    page.push(Row::new(0, line), &mut sink)?;
+                }
+                Ok(())
+            }
+            _ => {
+                file.seek(SeekFrom::Start(0))?;
+                self.run(
+                    BufReader::new(encoding.decode(file.take(len), name.to_owned())),
+                    name,
+                    page,
+                    sink,
+                )
+            }
+        }
+    }
+
+    // Push the rows of `reader` into `sink`. Reading stops as soon as nothing after the current
+    // line could be selected anymore: after the n-th match for TOP(n), past the last line of a
+    // LINES range, or once LIMIT is reached. `name` is the input, for errors about its records.
+    pub fn run<F>(
+        &self,
+        reader: impl BufRead,
+        name: &str,
+        page: &mut Page,
+        sink: F,
+    ) -> Result<(), InterpreterError>
+    where
+        F: FnMut(Row) -> Result<(), InterpreterError>,
+    {
+        match self.records {
+            Some(records) => self.run_rows(records.scan(reader, name), page, sink),
+            None => self.run_rows(scan(reader), page, sink),
+        }
+    }
+
+    fn run_rows<F>(
+        &self,
+        rows: impl Iterator<Item = Result<Row, InterpreterError>>,
+        page: &mut Page,
+        sink: F,
+    ) -> Result<(), InterpreterError>
+    where
+        F: FnMut(Row) -> Result<(), InterpreterError>,
+    {
+        self.select(self.filter(rows), page, sink)
+    }
+
+    // SELECT name, amount only keeps those columns, once WHERE and ORDER BY have seen all of them
+    fn project(&self, row: Row) -> Row {
+        match self.records {
+            Some(records) => records.project(row),
+            None => row,
+        }
+    }
+
+    // the rows in the LINES range that match the WHERE, up to the row after the range
+    fn filter<'b>(
+        &'b self,
+        rows: impl Iterator<Item = Result<Row, InterpreterError>> + 'b,
+    ) -> impl Iterator<Item = Result<Row, InterpreterError>> + 'b {
+        rows.take_while(|row| match (self.quantity, row) {
+            (Quantity::Lines(_, last), Ok(row)) => row.number <= last,
+            _ => true,
+        })
+        .filter(|row| match row {
+            Ok(row) => {
+                !matches!(self.quantity, Quantity::Lines(first, _) if row.number < first)
+                    && self
+                        .condition
+                        .is_none_or(|condition| condition.matches(row))
+            }
+            Err(_) => true,
+        })
+    }
+
+    // TOP/TAIL, then OFFSET/LIMIT, of the rows that got through the filter
+    fn select<T, F>(
+        &self,
+        mut rows: impl Iterator<Item = Result<T, InterpreterError>>,
+        page: &mut Page,
+        mut sink: F,
+    ) -> Result<(), InterpreterError>
+    where
+        F: FnMut(T) -> Result<(), InterpreterError>,
+    {
+        let mut tail = VecDeque::new();
+        let mut taken = 0;
+        //checked before every read, so not a single line more is read than needed
+        let done = |page: &Page, taken: usize| {
+            page.is_full() || matches!(self.quantity, Quantity::Top(n) if taken >= n)
+        };
+
+        while !done(page, taken) {
+            let row = match rows.next() {
+                Some(row) => row?,
+                None => break,
+            };
+
+            match self.quantity {
+                Quantity::Tail(n) => {
+                    if n == 0 {
+                        continue;
+                    }
+                    if tail.len() == n {
+                        tail.pop_front();
+                    }
+                    tail.push_back(row);
+                }
+                Quantity::Top(_) => {
+                    taken += 1;
+                    page.push(row, &mut sink)?;
+                }
+                Quantity::All | Quantity::Lines(..) => page.push(row, &mut sink)?,
+            }
+        }
+
+        for row in tail {
+            if page.is_full() {
+                break;
+            }
+            page.push(row, &mut sink)?;
+        }
+        Ok(())
+    }
+}
+
+// OFFSET/LIMIT over a stream: the first `offset` rows are dropped, then at most `limit` rows
+// go through.
+pub struct Page {
+    skip: usize,
+    left: Option<usize>,
+}
+
+impl Page {
+    fn new(offset: usize, limit: Option<usize>) -> Self {
+        Self {
+            skip: offset,
+            left: limit,
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.left == Some(0)
+    }
+
+    fn push<T, F>(&mut self, row: T, sink: &mut F) -> Result<(), InterpreterError>
+    where
+        F: FnMut(T) -> Result<(), InterpreterError>,
+    {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return Ok(());
+        }
+        if let Some(left) = self.left.as_mut() {
+            *left -= 1;
+        }
+        sink(row)
+    }
+}
+
+// Lines are numbered as they are read, so every row keeps its original line number through the
+// filtering.
+fn scan(reader: impl BufRead) -> impl Iterator<Item = Result<Row, InterpreterError>> {
+    reader.lines().enumerate().map(|(i, line)| match line {
+        Ok(line) => Ok(Row::new(i + 1, line)),
+        Err(e) => Err(encoding::read_error(e)),
+    })
+}
+
+// The last `n` lines in the first `len` bytes of a file, found the way tail(1) does it: read
+// blocks backwards from the end until enough newlines have gone by. Every line comes with the
+// offset it starts at, and is still undecoded.
+fn read_tail(mut file: &File, len: u64, n: usize) -> io::Result<Vec<(u64, Vec<u8>)>> {
+    if len == 0 || n == 0 {
+        return Ok(Vec::new());
+    }
+    //the final newline ends the last line, it does not start another one
+    let mut last = [0; 1];
+    file.seek(SeekFrom::Start(len - 1))?;
+    file.read_exact(&mut last)?;
+    let trailing_newline = last[0] == b'\n';
+
+    //blocks are kept newest first, and `start` is where the oldest one begins
+    let mut blocks: Vec<Vec<u8>> = Vec::new();
+    let mut start = len;
+    let mut newlines = 0;
+    while start > 0 && newlines < n + trailing_newline as usize {
+        let size = TAIL_BLOCK_SIZE.min(start);
+        start -= size;
+        let mut block = vec![0; size as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block)?;
+        newlines += block.iter().filter(|b| **b == b'\n').count();
+        blocks.push(block);
+    }
+
+    let data: Vec<u8> = blocks.iter().rev().flatten().copied().collect();
+    let mut lines = Vec::new();
+    let mut offset = start;
+    for line in data.split(|b| *b == b'\n') {
+        lines.push((offset, line.strip_suffix(b"\r").unwrap_or(line).to_vec()));
+        offset += line.len() as u64 + 1;
+    }
+    if trailing_newline {
+        lines.pop();
+    }
+    //unless we are at the start of the file, the first piece is the end of a longer line
+    if start > 0 {
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(n);
+    Ok(lines.split_off(skip))
+}
+
+// COUNTC/COUNTL/COUNTW folded over the rows as they go by.
+pub struct Counter {
+    counter: Token,
+    count: usize,
+}
+
+impl Counter {
+    pub fn new(counter: &Token) -> Self {
+        Self {
+            counter: counter.clone(),
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, row: &Row) {
+        self.count += match self.counter {
+            Token::CountC => row.line.chars().count(),
+            Token::CountL => 1,
+            Token::CountW => row.line.split_whitespace().count(),
+
+            _ => unreachable!(),
+        };
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        io::{self, Read},
+    };
+
+    use super::*;
+    use crate::ast::Expression;
+
+    // a reader over "1\n2\n...\n100\n" that remembers how many bytes were handed out
+    struct Counting<'a> {
+        inner: io::Cursor<Vec<u8>>,
+        read: &'a Cell<usize>,
+    }
+
+    impl Read for Counting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            //one byte at a time so a BufReader cannot read ahead
+            let len = buf.len().min(1);
+            let n = self.inner.read(&mut buf[..len])?;
+            self.read.set(self.read.get() + n);
+            Ok(n)
+        }
+    }
+
+    fn run(
+        quantity: Quantity,
+        offset: usize,
+        limit: Option<usize>,
+        condition: Option<&Condition>,
+    ) -> (Vec<usize>, usize) {
+        let content: String = (1..=100).map(|i| format!("{}\n", i)).collect();
+        let read = Cell::new(0);
+        let reader = io::BufReader::new(Counting {
+            inner: io::Cursor::new(content.into_bytes()),
+            read: &read,
+        });
+        let pipeline = Pipeline {
+            condition,
+            quantity,
+            offset,
+            limit,
+            numbered: true,
+            encoding: Encoding::default(),
+            records: None,
+            order: None,
+        };
+        let mut rows = Vec::new();
+        pipeline
+            .run(reader, "input", &mut pipeline.page(), |row| {
+                rows.push(row.number);
+                Ok(())
+            })
+            .unwrap();
+        (rows, read.get())
+    }
+
+    #[test]
+    fn it_stops_reading_once_nothing_more_can_be_selected() {
+        let (rows, read) = run(Quantity::Top(3), 0, None, None);
+        assert_eq!(rows, vec![1, 2, 3]);
+        assert!(read < 10, "read {} bytes for TOP(3)", read);
+
+        let (rows, read) = run(Quantity::Lines(5, 7), 1, None, None);
+        assert_eq!(rows, vec![6, 7]);
+        assert!(read < 20, "read {} bytes for LINES(5..7)", read);
+
+        let (rows, read) = run(Quantity::All, 2, Some(2), None);
+        assert_eq!(rows, vec![3, 4]);
+        assert!(read < 10, "read {} bytes for LIMIT 2 OFFSET 2", read);
+    }
+
+    #[test]
+    fn it_keeps_only_the_last_matches_for_tail() {
+        let condition = Condition::compile(&Expression::Predicate {
+            conditional: Token::Like,
+            target: "5".to_owned(),
+            escape: None,
+        })
+        .unwrap();
+
+        let (rows, _) = run(Quantity::Tail(3), 0, None, Some(&condition));
+        assert_eq!(rows, vec![75, 85, 95]);
+        let (rows, _) = run(Quantity::Tail(3), 1, Some(1), Some(&condition));
+        assert_eq!(rows, vec![85]);
+        let (rows, _) = run(Quantity::Tail(0), 0, None, None);
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn it_can_paginate_without_panicking_out_of_range() {
+        let (rows, _) = run(Quantity::Top(5), 1, Some(2), None);
+        assert_eq!(rows, vec![2, 3]);
+        let (rows, _) = run(Quantity::Top(5), 3, None, None);
+        assert_eq!(rows, vec![4, 5]);
+        let (rows, _) = run(Quantity::Top(5), 10, Some(2), None);
+        assert!(rows.is_empty());
+        let (rows, _) = run(Quantity::Tail(5), 4, Some(usize::MAX), None);
+        assert_eq!(rows, vec![100]);
+    }
+
+    #[test]
+    fn it_reads_tail_backwards_from_the_end_of_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let tail = |content: &[u8], n: usize| {
+            let path = dir.path().join("tail.log");
+            std::fs::write(&path, content).unwrap();
+            read_tail(&File::open(&path).unwrap(), content.len() as u64, n)
+                .unwrap()
+                .into_iter()
+                .map(|(_, line)| String::from_utf8_lossy(&line).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(tail(b"a\nb\nc\n", 2), vec!["b", "c"]);
+        assert_eq!(tail(b"a\r\nb\r\nc", 2), vec!["b", "c"]);
+        assert_eq!(tail(b"a\nb\n", 10), vec!["a", "b"]);
+        assert_eq!(tail(b"\n\n", 1), vec![""]);
+        assert!(tail(b"", 3).is_empty());
+        assert_eq!(tail(b"a\nb\xff\nc\n", 2), vec!["b\u{fffd}", "c"]);
+
+        //lines much longer than a block, and many more lines than blocks
+        let long = "x".repeat(TAIL_BLOCK_SIZE as usize * 2 + 7);
+        let content = format!("first\n{}\nlast\n", long);
+        assert_eq!(
+            tail(content.as_bytes(), 2),
+            vec![long.clone(), "last".to_owned()]
+        );
+        let content: String = (1..=100_000).map(|i| format!("{}\n", i)).collect();
+        assert_eq!(
+            tail(content.as_bytes(), 3),
+            vec!["99998", "99999", "100000"]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::io::BufRead;
+
+use chrono::{DateTime, Datelike, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+
+use crate::{
+    encoding,
+    interpreter::{InterpreterError, Row},
+    json::Columns,
+};
+
+// What a field of a preset is turned into. Timestamps become RFC 3339 in UTC, so they sort and
+// compare as text: WHERE time >= "2024-10-10T13:00" does what it says.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Text,
+    // "-" is null
+    Integer,
+    // 10/Oct/2024:13:55:36 +0000, as Apache and nginx write it
+    ClfTime,
+    // Oct 11 22:14:15, which has no year and no time zone
+    SyslogTime,
+    Rfc3339,
+}
+
+impl Type {
+    // a field that does not parse as its type is kept as it was
+    pub fn value(self, text: &str) -> Value {
+        let parsed = match self {
+            Type::Text => None,
+            Type::Integer if text == "-" => Some(Value::Null),
+            Type::Integer => text.parse::<i64>().ok().map(Value::from),
+            Type::ClfTime => DateTime::parse_from_str(text, "%d/%b/%Y:%H:%M:%S %z")
+                .ok()
+                .map(|time| timestamp(time.with_timezone(&Utc))),
+            Type::SyslogTime => syslog_time(text, Utc::now()).map(timestamp),
+            Type::Rfc3339 if text == "-" => Some(Value::Null),
+            Type::Rfc3339 => DateTime::parse_from_rfc3339(text)
+                .ok()
+                .map(|time| timestamp(time.with_timezone(&Utc))),
+        };
+        parsed.unwrap_or_else(|| text.into())
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> Value {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true).into()
+}
+
+// RFC 3164 leaves out the year, so it is this one, or last year for a date that would otherwise
+// be in the future (December's log read in January). The time is taken as UTC.
+fn syslog_time(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let at = |year: i32| {
+        NaiveDateTime::parse_from_str(&format!("{} {}", year, text), "%Y %b %e %H:%M:%S").ok()
+    };
+    let time = at(now.year())?.and_utc();
+    if time > now + chrono::Duration::days(1) {
+        return at(now.year() - 1).map(|time| time.and_utc());
+    }
+    Some(time)
+}
+
+// The log formats FROM "access.log" FORMAT nginx_combined knows. Most are a regex with named
+// groups, read like PARSE REGEX, logfmt has different keys on every line and is read like NDJSON.
+pub enum Preset {
+    Regex {
+        pattern: String,
+        types: &'static [(&'static str, Type)],
+    },
+    Logfmt,
+}
+
+// "GET /index.html HTTP/1.1" with a backslash before any quote in it, or "-" for a bad request
+const REQUEST: &str =
+    r#""(?P<request>(?P<method>[A-Z]+) (?P<path>\S+) (?P<protocol>[^"\\]+)|(?:[^"\\]|\\.)*)""#;
+const CLF_TYPES: &[(&str, Type)] = &[
+    ("time", Type::ClfTime),
+    ("status", Type::Integer),
+    ("bytes", Type::Integer),
+];
+
+pub const NAMES: &[&str] = &[
+    "apache_common",
+    "apache_combined",
+    "nginx_combined",
+    "syslog_rfc3164",
+    "syslog_rfc5424",
+    "logfmt",
+];
+
+impl Preset {
+    pub fn find(name: &str) -> Option<Self> {
+        let common = || {
+            format!(
+                r#"^(?P<host>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<time>[^\]]+)\] {} (?P<status>\d{{3}}) (?P<bytes>\d+|-)"#,
+                REQUEST
+            )
+        };
+        Some(match name.to_ascii_lowercase().as_str() {
+            "apache_common" => Preset::Regex {
+                pattern: format!("{}$", common()),
+                types: CLF_TYPES,
+            },
+            //nginx writes its default log format the same way Apache writes combined
+            "apache_combined" | "nginx_combined" => Preset::Regex {
+                pattern: format!(
+                    r#"{} "(?P<referer>(?:[^"\\]|\\.)*)" "(?P<user_agent>(?:[^"\\]|\\.)*)""#,
+                    common()
+                ),
+                types: CLF_TYPES,
+            },
+            "syslog_rfc3164" => Preset::Regex {
+                pattern: r"^(?:<(?P<priority>\d{1,3})>)?(?P<time>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (?P<host>\S+) (?:(?P<tag>[^:\[\s]+)(?:\[(?P<pid>\d+)\])?: )?(?P<message>.*)$".to_owned(),
+                types: &[("priority", Type::Integer), ("time", Type::SyslogTime), ("pid", Type::Integer)],
+            },
+            "syslog_rfc5424" => Preset::Regex {
+                pattern: r"^<(?P<priority>\d{1,3})>(?P<version>\d{1,2}) (?P<time>\S+) (?P<host>\S+) (?P<app>\S+) (?P<procid>\S+) (?P<msgid>\S+) (?P<structured_data>-|(?:\[(?:[^\]\\]|\\.)*\])+)(?: (?P<message>.*))?$".to_owned(),
+                types: &[("priority", Type::Integer), ("version", Type::Integer), ("time", Type::Rfc3339)],
+            },
+            "logfmt" => Preset::Logfmt,
+            _ => return None,
+        })
+    }
+}
+
+// level=info msg="disk full" took=12ms, as a JSON object in the order of the line. A key without
+// a value is true, whole numbers and decimals are numbers, and time, ts and timestamp are
+// RFC 3339 timestamps like in the other presets.
+pub fn logfmt(line: &str) -> Value {
+    let mut members = Map::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..end];
+        rest = &rest[end..];
+        let value = match rest.strip_prefix('=') {
+            Some(after) if after.starts_with('"') => {
+                let (value, after) = quoted(&after[1..]);
+                rest = after;
+                value.into()
+            }
+            Some(after) => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                rest = &after[end..];
+                typed(key, &after[..end])
+            }
+            None => Value::Bool(true),
+        };
+        if !key.is_empty() {
+            members.insert(key.to_owned(), value);
+        }
+        rest = rest.trim_start();
+    }
+    Value::Object(members)
+}
+
+// the rest of a quoted value and what comes after its closing quote
+fn quoted(text: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return (value, &text[i + 1..]),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, c)) => value.push(c),
+                None => value.push('\\'),
+            },
+            c => value.push(c),
+        }
+    }
+    (value, "")
+}
+
+fn typed(key: &str, value: &str) -> Value {
+    if matches!(key, "time" | "ts" | "timestamp") {
+        return Type::Rfc3339.value(value);
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        return n.into();
+    }
+    match value.parse::<f64>() {
+        Ok(n) if n.is_finite() => n.into(),
+        _ => value.into(),
+    }
+}
+
+// every line that is not blank is a record, logfmt has no way to be malformed
+pub fn scan_logfmt<'a>(
+    reader: impl BufRead + 'a,
+    columns: &'a Columns,
+) -> impl Iterator<Item = Result<Row, InterpreterError>> + 'a {
+    reader
+        .lines()
+        .enumerate()
+        .filter_map(move |(i, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => {
+                let record = logfmt(&line);
+                Some(Ok(columns.row(i + 1, line, &record)))
+            }
+            Err(e) => Some(Err(encoding::read_error(e))),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::Captures;
+    use serde_json::json;
+
+    fn parse(preset: &str, line: &str) -> Row {
+        let Some(Preset::Regex { pattern, types }) = Preset::find(preset) else {
+            panic!("{} is not a regex preset", preset);
+        };
+        let captures = Captures::new(&pattern, false).unwrap().typed(types);
+        let row = captures.scan_lines(line.as_bytes()).next();
+        row.unwrap_or_else(|| panic!("{} does not match {}", preset, line))
+            .unwrap()
+    }
+
+    #[test]
+    fn it_can_parse_access_logs() {
+        let row = parse(
+            "nginx_combined",
+            r#"10.0.0.1 - frank [10/Oct/2024:13:55:36 -0700] "GET /a?b=\"c\" HTTP/1.1" 200 2326 "-" "curl/8.0""#,
+        );
+        assert_eq!(row.fields.get("time"), Some(&json!("2024-10-10T20:55:36Z")));
+        assert_eq!(row.fields.get("status"), Some(&json!(200)));
+        assert_eq!(row.fields.get("path"), Some(&json!(r#"/a?b=\"c\""#)));
+        assert_eq!(row.fields.get("user_agent"), Some(&json!("curl/8.0")));
+
+        let row = parse(
+            "apache_common",
+            r#"::1 - - [01/Jan/2025:00:00:00 +0000] "-" 408 -"#,
+        );
+        assert_eq!(row.fields.get("request"), Some(&json!("-")));
+        assert_eq!(row.fields.get("method"), Some(&Value::Null));
+        assert_eq!(row.fields.get("bytes"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn it_can_parse_syslog() {
+        let row = parse("syslog_rfc5424", "<165>1 2003-10-11T22:14:15.003+02:00 mymachine evntslog - ID47 [exampleSDID@32473 iut=\"3\"] An application event");
+        assert_eq!(row.fields.get("priority"), Some(&json!(165)));
+        assert_eq!(
+            row.fields.get("time"),
+            Some(&json!("2003-10-11T20:14:15.003Z"))
+        );
+        assert_eq!(
+            row.fields.get("message"),
+            Some(&json!("An application event"))
+        );
+
+        let row = parse(
+            "syslog_rfc3164",
+            "<34>Oct  1 22:14:15 mymachine su[123]: 'su root' failed",
+        );
+        assert_eq!(row.fields.get("tag"), Some(&json!("su")));
+        assert_eq!(row.fields.get("pid"), Some(&json!(123)));
+
+        let now = DateTime::parse_from_rfc3339("2025-01-02T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let time = |text: &str| syslog_time(text, now).map(|time| time.to_rfc3339());
+        assert_eq!(
+            time("Jan  1 10:00:00"),
+            Some("2025-01-01T10:00:00+00:00".to_owned())
+        );
+        assert_eq!(
+            time("Dec 31 23:59:59"),
+            Some("2024-12-31T23:59:59+00:00".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_can_parse_logfmt() {
+        assert_eq!(
+            logfmt(
+                r#"time=2024-10-10T13:55:36+01:00 level=info msg="say \"hi\"" took=1.5 n=3 debug path=/a"#
+            ),
+            json!({"time": "2024-10-10T12:55:36Z", "level": "info", "msg": "say \"hi\"", "took": 1.5, "n": 3, "debug": true, "path": "/a"})
+        );
+        assert_eq!(logfmt("  "), json!({}));
+    }
+}
@@ -0,0 +1,364 @@
+use std::{borrow::Cow, io::BufRead, sync::Arc};
+
+use csv::{ReaderBuilder, StringRecord};
+use serde_json::Value;
+
+use crate::{
+    ast::RecordFormat,
+    capture::Captures,
+    encoding,
+    interpreter::{InterpreterError, Row},
+    json::{self, Columns},
+    preset::{self, Preset},
+};
+
+// what a record too short to have a column has in it
+static EMPTY: Value = Value::String(String::new());
+
+/// The named values of a row that has columns, like a CSV record. A plain line has none. CSV
+/// fields are always strings, JSON ones keep their type.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fields {
+    // shared by every row of an input
+    names: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+impl Fields {
+    pub fn new(names: Arc<[String]>, values: Vec<Value>) -> Self {
+        Self { names, values }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// a record too short to have the column has "" in it
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let i = self.names.iter().position(|column| column == name)?;
+        Some(self.values.get(i).unwrap_or(&EMPTY))
+    }
+
+    // without the first `n`
+    pub(crate) fn skip(self, n: usize) -> Self {
+        if n == 0 {
+            return self;
+        }
+        Self {
+            names: self.names[n..].into(),
+            values: self.values.into_iter().skip(n).collect(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), self.values.get(i).unwrap_or(&EMPTY)))
+    }
+}
+
+// a field as text: strings as they are, null as nothing and everything else as JSON
+pub fn text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Null => Cow::Borrowed(""),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+// How a SELECT reads an input that has columns: FROM CSV "data.csv" HEADER. Every record becomes
+// one row, numbered with the line it starts on (a quoted field can go over many lines), and its
+// line is the record written back out with the same delimiter. With HEADER the first record
+// names the columns, without it they are c1, c2, c3...
+//
+// FROM JSON and FROM NDJSON records have no fixed columns, every column is a path into them (see
+// json.rs), and PARSE REGEX takes its columns from the groups of the regex (see capture.rs).
+// FORMAT presets are one or the other, see preset.rs.
+pub struct Records {
+    layout: Layout,
+    // SELECT name, amount, every column when None
+    columns: Option<Arc<[String]>>,
+    // every column the SELECT refers to, which has to be in each input it reads
+    required: Vec<String>,
+}
+
+enum Layout {
+    Csv { delimiter: char, header: bool },
+    // the paths of every column the SELECT refers to
+    Json(Columns),
+    Ndjson(Columns),
+    Logfmt(Columns),
+    Regex(Captures),
+}
+
+impl Records {
+    // `referenced` are the columns of WHERE and ORDER BY, which are needed but not kept
+    pub fn new(
+        format: RecordFormat,
+        columns: &[String],
+        referenced: &[&str],
+    ) -> Result<Self, InterpreterError> {
+        let mut required: Vec<String> = columns.to_vec();
+        required.extend(referenced.iter().map(|column| column.to_string()));
+        let members = columns.is_empty();
+        let layout = match format {
+            RecordFormat::Csv { delimiter, header } => Layout::Csv { delimiter, header },
+            RecordFormat::Json => Layout::Json(Columns::new(&required, members)?),
+            RecordFormat::Ndjson => Layout::Ndjson(Columns::new(&required, members)?),
+            RecordFormat::Regex { pattern, unparsed } => {
+                Layout::Regex(Captures::new(&pattern, unparsed)?)
+            }
+            RecordFormat::Preset { name, unparsed } => match Preset::find(&name) {
+                Some(Preset::Regex { pattern, types }) => {
+                    Layout::Regex(Captures::new(&pattern, unparsed)?.typed(types))
+                }
+                Some(Preset::Logfmt) => Layout::Logfmt(Columns::new(&required, members)?),
+                None => {
+                    return Err(InterpreterError::UnknownFormat(
+                        name,
+                        preset::NAMES.join(", "),
+                    ))
+                }
+            },
+        };
+        //the columns of a regex are known before a single line is read
+        if let Layout::Regex(captures) = &layout {
+            if let Some(missing) = required
+                .iter()
+                .find(|column| !captures.names().contains(column))
+            {
+                return Err(InterpreterError::UnknownColumn(
+                    missing.clone(),
+                    captures.names().join(", "),
+                ));
+            }
+        }
+        Ok(Self {
+            layout,
+            columns: (!columns.is_empty()).then(|| columns.into()),
+            required,
+        })
+    }
+
+    // the records of `input`, which is only there to say where a malformed one is
+    pub fn scan<'a, R: BufRead + 'a>(
+        &'a self,
+        reader: R,
+        input: &'a str,
+    ) -> Box<dyn Iterator<Item = Result<Row, InterpreterError>> + 'a> {
+        match &self.layout {
+            Layout::Csv { delimiter, .. } => Box::new(Scan {
+                records: self,
+                reader: ReaderBuilder::new()
+                    .delimiter(*delimiter as u8)
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_reader(reader),
+                record: StringRecord::new(),
+                names: None,
+            }),
+            Layout::Json(paths) => json::scan_document(reader, input, paths),
+            Layout::Ndjson(paths) => Box::new(json::scan_lines(reader, input, paths)),
+            Layout::Logfmt(paths) => Box::new(preset::scan_logfmt(reader, paths)),
+            Layout::Regex(captures) => Box::new(captures.scan_lines(reader)),
+        }
+    }
+
+    // only the columns the SELECT asked for, in the order it asked for them
+    pub fn project(&self, row: Row) -> Row {
+        let Some(columns) = &self.columns else {
+            //the members of a JSON or logfmt record come after the columns WHERE and ORDER BY needed
+            return match &self.layout {
+                Layout::Json(paths) | Layout::Ndjson(paths) | Layout::Logfmt(paths) => Row {
+                    fields: row.fields.skip(paths.len()),
+                    ..row
+                },
+                Layout::Csv { .. } | Layout::Regex(_) => row,
+            };
+        };
+        let values: Vec<Value> = columns
+            .iter()
+            .map(|column| row.fields.get(column).cloned().unwrap_or(Value::Null))
+            .collect();
+        Row {
+            number: row.number,
+            line: self.join(&values),
+            fields: Fields::new(columns.clone(), values),
+        }
+    }
+
+    // CSV goes back out with its own delimiter, any other columns are separated by tabs
+    fn join(&self, values: &[Value]) -> String {
+        match self.layout {
+            Layout::Csv { delimiter, .. } => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|value| quote(&text(value), delimiter).into_owned())
+                    .collect();
+                values.join(delimiter.encode_utf8(&mut [0; 4]))
+            }
+            _ => {
+                let values: Vec<Cow<str>> = values.iter().map(text).collect();
+                values.join("\t")
+            }
+        }
+    }
+}
+
+pub struct Scan<'a, R> {
+    records: &'a Records,
+    reader: csv::Reader<R>,
+    record: StringRecord,
+    // known once the first record has been read
+    names: Option<Arc<[String]>>,
+}
+
+impl<R: BufRead> Scan<'_, R> {
+    fn read(&mut self) -> Result<Option<Row>, InterpreterError> {
+        if !self
+            .reader
+            .read_record(&mut self.record)
+            .map_err(read_error)?
+        {
+            return Ok(None);
+        }
+        let names = match &self.names {
+            Some(names) => names.clone(),
+            None => {
+                let header = matches!(self.records.layout, Layout::Csv { header: true, .. });
+                let names: Arc<[String]> = if header {
+                    self.record
+                        .iter()
+                        .map(|name| name.trim().to_owned())
+                        .collect()
+                } else {
+                    (1..=self.record.len()).map(|i| format!("c{}", i)).collect()
+                };
+                if let Some(missing) = self
+                    .records
+                    .required
+                    .iter()
+                    .find(|column| !names.contains(column))
+                {
+                    return Err(InterpreterError::UnknownColumn(
+                        missing.clone(),
+                        names.join(", "),
+                    ));
+                }
+                self.names = Some(names.clone());
+                if header {
+                    return self.read();
+                }
+                names
+            }
+        };
+
+        let values: Vec<Value> = self
+            .record
+            .iter()
+            .map(|value| Value::String(value.to_owned()))
+            .collect();
+        Ok(Some(Row {
+            number: self
+                .record
+                .position()
+                .map_or(0, |position| position.line() as usize),
+            line: self.records.join(&values),
+            fields: Fields::new(names, values),
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for Scan<'_, R> {
+    type Item = Result<Row, InterpreterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+// quoted only when it has to be, the way RFC 4180 says
+pub fn quote(field: &str, delimiter: char) -> Cow<'_, str> {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+// the input is decoded before it gets here, so anything but an io::Error cannot happen
+fn read_error(e: csv::Error) -> InterpreterError {
+    match e.into_kind() {
+        csv::ErrorKind::Io(e) => encoding::read_error(e),
+        kind => InterpreterError::IOError(std::io::Error::other(format!("{:?}", kind))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(
+        format: RecordFormat,
+        columns: &[&str],
+        input: &str,
+    ) -> Result<Vec<Row>, InterpreterError> {
+        let columns: Vec<String> = columns.iter().map(|column| column.to_string()).collect();
+        let records = Records::new(format, &columns, &[])?;
+        records
+            .scan(input.as_bytes(), "input")
+            .map(|row| row.map(|row| records.project(row)))
+            .collect()
+    }
+
+    #[test]
+    fn it_can_read_quoted_and_multiline_fields() {
+        let csv = RecordFormat::Csv {
+            delimiter: ',',
+            header: true,
+        };
+        let input = "name,amount,note\nann,120,\"says \"\"hi\"\"\"\nbob,80,\"two\nlines\"\ncid,7\n";
+        let rows = rows(csv, &[], input).unwrap();
+
+        assert_eq!(
+            rows.iter().map(|row| row.number).collect::<Vec<_>>(),
+            vec![2, 3, 5]
+        );
+        assert_eq!(rows[0].fields.get("note"), Some(&json!("says \"hi\"")));
+        assert_eq!(rows[0].line, "ann,120,\"says \"\"hi\"\"\"");
+        assert_eq!(rows[1].fields.get("note"), Some(&json!("two\nlines")));
+        //a short record has empty fields, an unknown column has none at all
+        assert_eq!(rows[2].fields.get("note"), Some(&json!("")));
+        assert_eq!(rows[2].fields.get("nope"), None);
+    }
+
+    #[test]
+    fn it_can_project_columns_without_a_header() {
+        let tsv = RecordFormat::Csv {
+            delimiter: '\t',
+            header: false,
+        };
+        let projected = rows(tsv, &["c3", "c1"], "a\tb\tc,d\n1\t2\t3\n").unwrap();
+
+        assert_eq!(projected[0].line, "c,d\ta");
+        assert_eq!(
+            projected[1].fields.iter().collect::<Vec<_>>(),
+            vec![("c3", &json!("3")), ("c1", &json!("1"))]
+        );
+
+        let csv = RecordFormat::Csv {
+            delimiter: ',',
+            header: true,
+        };
+        assert!(matches!(
+            rows(csv, &["amout"], "name,amount\nann,1\n"),
+            Err(InterpreterError::UnknownColumn(column, columns)) if column == "amout" && columns == "name, amount"
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    ast::Format,
+    interpreter::{InterpreterError, Row},
+    record::Fields,
+    token::Token,
+};
+
+/// What a statement produces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Results {
+    /// the matching lines of a SELECT
+    Rows,
+    /// a count per file, named after what is counted: "lines" for COUNTL, "replaced" for REPLACE...
+    Counts(&'static str),
+    /// nothing to show, the statement only changed files (or its results went to an export)
+    Done,
+}
+
+impl Results {
+    pub fn for_counter(counter: Option<&Token>) -> Self {
+        match counter {
+            None => Results::Rows,
+            Some(Token::CountC) => Results::Counts("chars"),
+            Some(Token::CountL) => Results::Counts("lines"),
+            Some(Token::CountW) => Results::Counts("words"),
+
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Told to a Sink before the results of a statement, so it knows what is coming and how the
+/// query asked for it to be shown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Header {
+    pub results: Results,
+    pub format: Format,
+    /// the results come from many files, so text output says which file each one is from
+    pub prefixed: bool,
+    /// WITH LINENO
+    pub numbered: bool,
+}
+
+impl Header {
+    pub fn done() -> Self {
+        Self {
+            results: Results::Done,
+            format: Format::Text,
+            prefixed: false,
+            numbered: false,
+        }
+    }
+}
+
+/// Where the results of statements go as they are produced. The interpreter never prints
+/// anything itself: the command line hands it a Printer, and everything else can collect the
+/// results into QueryResults, or do whatever it wants with them as they stream by.
+//
+/// Every statement is one begin, then its rows or counts, then one end.
+pub trait Sink {
+    fn begin(&mut self, header: &Header) -> Result<(), InterpreterError>;
+
+    fn row(&mut self, file: &str, row: Row) -> Result<(), InterpreterError>;
+
+    /// the count for one file, or the total over all of them when there is no file
+    fn count(&mut self, file: Option<&str>, count: usize) -> Result<(), InterpreterError>;
+
+    fn end(&mut self) -> Result<(), InterpreterError>;
+
+    /// TAIL FOLLOW calls this after every row, it may be a while until the next one
+    fn flush(&mut self) -> Result<(), InterpreterError> {
+        Ok(())
+    }
+
+    /// Finding a line's number can cost a full scan (TAIL reads backwards from the end of a file
+    /// otherwise), so a sink that never looks at them can say so.
+    fn needs_line_numbers(&self, _header: &Header) -> bool {
+        true
+    }
+}
+
+/// one line of a result and the input it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultRow {
+    pub file: String,
+    pub number: usize,
+    pub line: String,
+    /// the columns of a record, see Fields
+    pub fields: Fields,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Count {
+    /// None for the total over all files
+    pub file: Option<String>,
+    pub count: usize,
+}
+
+/// Everything one statement produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub results: Results,
+    pub rows: Vec<ResultRow>,
+    pub counts: Vec<Count>,
+}
+
+/// A Sink that keeps every result, one QueryResult per statement.
+#[derive(Debug, Default)]
+pub struct Collector {
+    pub results: Vec<QueryResult>,
+}
+
+impl Collector {
+    fn current(&mut self) -> &mut QueryResult {
+        self.results.last_mut().expect("results before begin")
+    }
+}
+
+impl Sink for Collector {
+    fn begin(&mut self, header: &Header) -> Result<(), InterpreterError> {
+        self.results.push(QueryResult {
+            results: header.results,
+            rows: Vec::new(),
+            counts: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn row(&mut self, file: &str, row: Row) -> Result<(), InterpreterError> {
+        self.current().rows.push(ResultRow {
+            file: file.to_owned(),
+            number: row.number,
+            line: row.line,
+            fields: row.fields,
+        });
+        Ok(())
+    }
+
+    fn count(&mut self, file: Option<&str>, count: usize) -> Result<(), InterpreterError> {
+        self.current().counts.push(Count {
+            file: file.map(str::to_owned),
+            count,
+        });
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), InterpreterError> {
+        Ok(())
+    }
+}
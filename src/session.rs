@@ -0,0 +1,269 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::{
+    ast::{Export, Format, Source, Statement},
+    interpreter::{self, InterpreterError, Options},
+    result::Sink,
+    source, Error,
+};
+
+/// Where and how the statements of a service run: the sink their results go to, the directory
+/// relative paths are resolved against (the process's current directory is shared by everything
+/// in it, so it is left alone), and limits on what a query may do.
+///
+/// ```
+/// use fql::{Collector, Session};
+/// # let logs = tempfile::tempdir().unwrap();
+/// # std::fs::write(logs.path().join("app.log"), "ERROR one\nok\nERROR two\n").unwrap();
+/// # let logs = logs.path();
+///
+/// let mut session = Session::new(Collector::default()).working_dir(logs).max_rows(1000).read_only(true);
+/// session.run(r#"SELECT TAIL(50) FROM "app.log" WHERE LIKE "ERROR""#)?;
+/// let results = session.into_sink().results;
+/// assert_eq!(results[0].rows.len(), 2);
+/// # Ok::<(), fql::Error>(())
+/// ```
+pub struct Session<S: Sink> {
+    sink: S,
+    working_dir: Option<PathBuf>,
+    max_rows: Option<usize>,
+    read_only: bool,
+    format: Option<Format>,
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl<S: Sink> Session<S> {
+    pub fn new(sink: S) -> Self {
+        Self {
+            sink,
+            working_dir: None,
+            max_rows: None,
+            read_only: false,
+            format: None,
+            stop: None,
+        }
+    }
+
+    /// relative paths in statements are relative to `dir`, and results name files with it in front
+    pub fn working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    /// a statement that produces more rows than this fails once it gets there, exports included
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// refuse scripts that would write files: CREATE, UPDATE, REPLACE, DELETE and exports
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// the OUTPUT of every SELECT that does not have one
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// A TAIL FOLLOW runs until `stop` is set, and the rest of the script runs after it. The flag is
+    /// left as it is, so clear it before the next FOLLOW.
+    pub fn stop_flag(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// parse `script` and run it
+    pub fn run(&mut self, script: &str) -> Result<(), Error> {
+        let program = crate::parse(script)?;
+        Ok(self.execute(&program)?)
+    }
+
+    /// run statements that are already parsed, see fql::parse
+    pub fn execute(&mut self, program: &[Statement]) -> Result<(), InterpreterError> {
+        //checked up front, a read-only session never runs half a script
+        if self.read_only {
+            if let Some(target) = program.iter().find_map(written_file) {
+                return Err(InterpreterError::ReadOnly(target.to_owned()));
+            }
+        }
+
+        let program: Vec<Statement> = program
+            .iter()
+            .map(|statement| {
+                let mut statement = statement.clone();
+                if let Some(dir) = &self.working_dir {
+                    resolve(&mut statement, dir);
+                }
+                if let Statement::SelectStatement {
+                    output: output @ None,
+                    ..
+                } = &mut statement
+                {
+                    *output = self.format;
+                }
+                statement
+            })
+            .collect();
+        let options = Options {
+            max_rows: self.max_rows,
+            stop: self.stop.as_deref(),
+        };
+        interpreter::execute_with(&program, &mut self.sink, options)
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// the sink back, with everything that was run through it
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+// the file a statement would write to, if it writes to one
+fn written_file(statement: &Statement) -> Option<&str> {
+    match statement {
+        Statement::SelectStatement { export, .. } => match export.as_ref()? {
+            Export::Overwrite(target) | Export::Append(target) => Some(target),
+        },
+        Statement::CreateStatement { target, .. }
+        | Statement::UpdateStatement { target, .. }
+        | Statement::ReplaceStatement { target, .. }
+        | Statement::DeleteStatement { target, .. } => Some(target),
+    }
+}
+
+fn resolve(statement: &mut Statement, dir: &Path) {
+    match statement {
+        Statement::SelectStatement { source, export, .. } => {
+            match source {
+                Source::Path(target) if source::is_glob(target) => {
+                    //the directory is taken literally, only the target is a pattern
+                    let dir = glob::Pattern::escape(&dir.to_string_lossy());
+                    *target = join(Path::new(&dir), target)
+                }
+                Source::Path(target) => *target = join(dir, target),
+                Source::Dir(source) => source.path = join(dir, &source.path),
+                Source::Stdin => {}
+            }
+            if let Some(Export::Overwrite(target) | Export::Append(target)) = export {
+                *target = join(dir, target);
+            }
+        }
+        Statement::CreateStatement { target, .. }
+        | Statement::UpdateStatement { target, .. }
+        | Statement::ReplaceStatement { target, .. }
+        | Statement::DeleteStatement { target, .. } => *target = join(dir, target),
+    }
+}
+
+fn join(dir: &Path, target: &str) -> String {
+    //an empty path stays empty so it is still reported as one
+    if target.is_empty() || Path::new(target).is_absolute() {
+        return target.to_owned();
+    }
+    dir.join(target).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::result::{Collector, Results};
+
+    #[test]
+    fn it_runs_scripts_in_its_working_directory_within_its_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+        fs::write(
+            dir.path().join("logs/app.log"),
+            "ERROR one\nok\nERROR two\nERROR three\n",
+        )
+        .unwrap();
+
+        let mut session = Session::new(Collector::default())
+            .working_dir(dir.path())
+            .max_rows(3);
+        session
+            .run(r#"SELECT * FROM "logs/app.log" WHERE LIKE "ERROR"; SELECT COUNTL * FROM "logs/*.log";"#)
+            .unwrap();
+        let results = &session.sink().results;
+        assert_eq!(results[0].rows.len(), 3);
+        assert_eq!(results[1].results, Results::Counts("lines"));
+        assert_eq!(results[1].counts[0].count, 4);
+        assert_eq!(
+            results[1].counts[0].file.as_deref(),
+            Some(dir.path().join("logs/app.log").to_str().unwrap())
+        );
+
+        assert!(matches!(
+            session.run(r#"SELECT * FROM "logs/app.log""#),
+            Err(Error::Interpreter(InterpreterError::TooManyRows(3)))
+        ));
+        //an export is no way around it
+        assert!(matches!(
+            session.run(r#"SELECT * FROM "logs/app.log" > "out.txt""#),
+            Err(Error::Interpreter(InterpreterError::TooManyRows(3)))
+        ));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("out.txt"))
+                .unwrap()
+                .lines()
+                .count(),
+            3
+        );
+        assert!(matches!(session.run("SELECT * FROM"), Err(Error::Parse(_))));
+
+        let mut session = Session::new(Collector::default())
+            .working_dir(dir.path())
+            .read_only(true);
+        assert!(matches!(
+            session
+                .run(r#"SELECT * FROM "logs/app.log"; DELETE FROM "logs/app.log" WHERE LIKE "ok""#),
+            Err(Error::Interpreter(InterpreterError::ReadOnly(_)))
+        ));
+        //nothing ran, not even the SELECT before the DELETE
+        assert!(session.sink().results.is_empty());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("logs/app.log"))
+                .unwrap()
+                .lines()
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn it_takes_a_working_directory_with_glob_characters_literally() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = dir.path().join("work[1]");
+        fs::create_dir(&work).unwrap();
+        fs::write(work.join("app.log"), "one\ntwo\n").unwrap();
+        let file = |name: &str| work.join(name).to_string_lossy().into_owned();
+
+        let mut session = Session::new(Collector::default()).working_dir(&work);
+        session
+            .run(r#"CREATE "new.txt"; SELECT * FROM "app.log" > "out.txt"; SELECT * FROM "app.log"; SELECT COUNTL * FROM "*.log";"#)
+            .unwrap();
+        assert!(work.join("new.txt").is_file());
+        assert_eq!(
+            fs::read_to_string(work.join("out.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+        let results = &session.sink().results;
+        assert_eq!(results[2].rows[1].file, file("app.log"));
+        assert_eq!(results[3].counts[0].file, Some(file("app.log")));
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+
+use crate::{
+    ast::{DirSource, Export, Source},
+    interpreter::InterpreterError,
+};
+
+// One thing a SELECT reads lines from.
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    // a reader that hands out lines as they come, stdin is never read into memory in one go
+    pub fn open(&self) -> Result<Box<dyn BufRead>, InterpreterError> {
+        match self {
+            Input::Stdin => Ok(Box::new(io::stdin().lock())),
+            Input::File(path) => match File::open(path) {
+                Ok(file) => Ok(Box::new(BufReader::new(file))),
+                Err(_) => Err(InterpreterError::InvalidOrUnavailableFile(
+                    path.display().to_string(),
+                )),
+            },
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Input::Stdin => None,
+            Input::File(path) => Some(path),
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            //the same name grep uses
+            Input::Stdin => write!(f, "(standard input)"),
+            Input::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// The inputs a FROM refers to, and whether results need a file name in front of them. STDIN and
+// a plain path are used as they are; a glob ("logs/*.log", "src/**/*.rs") and a DIR expand to
+// every matching file in sorted order, leaving out the file the results are exported to. A file
+// that is there is read as it is even with [ or * in its path, like one in a directory work[1].
+pub fn expand(
+    source: &Source,
+    export: Option<&Export>,
+) -> Result<(Vec<Input>, bool), InterpreterError> {
+    let export = export.map(|export| match export {
+        Export::Overwrite(path) | Export::Append(path) => Path::new(path),
+    });
+    let (paths, target) = match source {
+        Source::Stdin => return Ok((vec![Input::Stdin], false)),
+        Source::Path(target) if !is_glob(target) || Path::new(target).is_file() => {
+            return Ok((vec![Input::File(PathBuf::from(target))], false))
+        }
+        Source::Path(target) => (expand_glob(target)?, target),
+        Source::Dir(dir) => (walk_dir(dir)?, &dir.path),
+    };
+
+    let sources: Vec<Input> = paths
+        .into_iter()
+        .filter(|path| !export.is_some_and(|export| is_same_file(path, export)))
+        .map(Input::File)
+        .collect();
+    if sources.is_empty() {
+        return Err(InterpreterError::NoMatchingFiles(target.to_owned()));
+    }
+    Ok((sources, true))
+}
+
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+pub fn is_glob(target: &str) -> bool {
+    target.contains(['*', '?', '['])
+}
+
+fn expand_glob(target: &str) -> Result<Vec<PathBuf>, InterpreterError> {
+    let paths = glob::glob(target)
+        .map_err(|e| InterpreterError::InvalidGlob(target.to_owned(), e.to_string()))?;
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.map_err(|e| InterpreterError::IOError(e.into()))?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+// FROM DIR walks a directory like ripgrep does: .gitignore and .ignore files are honored (even
+// outside a git repository) and hidden files are skipped unless asked for. INCLUDE/EXCLUDE globs
+// are relative to the directory being walked.
+fn walk_dir(dir: &DirSource) -> Result<Vec<PathBuf>, InterpreterError> {
+    let root = Path::new(&dir.path);
+    if dir.path.is_empty() || !root.is_dir() {
+        return Err(InterpreterError::InvalidOrUnavailableFile(dir.path.clone()));
+    }
+
+    let mut overrides = OverrideBuilder::new(root);
+    for include in &dir.include {
+        overrides
+            .add(include)
+            .map_err(|e| InterpreterError::InvalidGlob(include.clone(), e.to_string()))?;
+    }
+    for exclude in &dir.exclude {
+        overrides
+            .add(&format!("!{}", exclude))
+            .map_err(|e| InterpreterError::InvalidGlob(exclude.clone(), e.to_string()))?;
+    }
+    let overrides = overrides
+        .build()
+        .map_err(|e| InterpreterError::InvalidGlob(dir.path.clone(), e.to_string()))?;
+
+    let mut walker = WalkBuilder::new(root);
+    walker
+        .overrides(overrides)
+        .hidden(!dir.hidden)
+        .follow_links(dir.follow_links)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if !dir.recursive {
+        walker.max_depth(Some(1));
+    }
+
+    let mut files = Vec::new();
+    for entry in walker.build() {
+        let entry =
+            entry.map_err(|e| InterpreterError::WalkFailed(dir.path.clone(), e.to_string()))?;
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+            || (dir.follow_links && entry.path().is_file())
+        {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_source(path: &Path) -> DirSource {
+        DirSource {
+            path: path.to_string_lossy().into_owned(),
+            recursive: true,
+            include: vec![],
+            exclude: vec![],
+            hidden: false,
+            follow_links: false,
+        }
+    }
+
+    fn relative(root: &Path, source: &DirSource) -> Vec<String> {
+        walk_dir(source)
+            .unwrap()
+            .iter()
+            .map(|path| {
+                path.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_can_walk_a_dir_with_filters_and_ignore_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            "main.rs",
+            "lib.rs",
+            "Cargo.toml",
+            "src/a.rs",
+            "target/debug/b.rs",
+            "build/c.rs",
+            ".hidden/d.rs",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "fn main() {}\n").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+        let mut source = dir_source(root);
+        source.include = vec!["*.rs".to_owned()];
+        source.exclude = vec!["target/**".to_owned()];
+        assert_eq!(
+            relative(root, &source),
+            vec!["lib.rs", "main.rs", "src/a.rs"]
+        );
+
+        source.hidden = true;
+        assert_eq!(
+            relative(root, &source),
+            vec![".hidden/d.rs", "lib.rs", "main.rs", "src/a.rs"]
+        );
+
+        source.recursive = false;
+        assert_eq!(relative(root, &source), vec!["lib.rs", "main.rs"]);
+    }
+}
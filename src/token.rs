@@ -1,34 +1,73 @@
-use logos::{Logos, Lexer};
+use logos::{Lexer, Logos};
 
-
-
-pub fn generate(input: &str) -> Vec<Token>{
+pub fn generate(input: &str) -> Vec<Token> {
     Token::lexer(input).collect()
 }
 
-fn to_string(lex: &mut Lexer<Token>) -> Option<String>{
+fn to_string(lex: &mut Lexer<Token>) -> Option<String> {
     //return the input string slice that matches the spec in Token enum
     let mut word = lex.slice().to_string();
 
-    if word.starts_with('"') && word.ends_with("\""){
+    if (word.starts_with('"') && word.ends_with('"'))
+        || (word.starts_with('\'') && word.ends_with('\''))
+        || word.starts_with('`')
+    {
         word.remove(0);
-        word.remove(word.len()-1);
+        word.remove(word.len() - 1);
     }
-    
+
     Some(word)
 }
-fn transform_top_and_tail(lex: &mut Lexer<Token>) -> Option<String>{
+fn transform_top_and_tail(lex: &mut Lexer<Token>) -> Option<String> {
     //return the input string slice that matches the spec in Token enum
     Some(lex.slice().to_string().to_uppercase())
 }
-fn to_float(lex: &mut Lexer<Token>) -> Option<f64>{
-    Some(lex.slice().parse().ok()?)
+fn to_float(lex: &mut Lexer<Token>) -> Option<f64> {
+    lex.slice().parse().ok()
 }
 
 #[derive(Debug, Clone, Logos, PartialEq)]
-pub enum Token{
+pub enum Token {
     #[token("CREATE", ignore(ascii_case))] //create "~/home/files/new_file.txt"
     Create,
+    #[token("IF", ignore(ascii_case))] //create if not exists "~/home/files/new_file.txt"
+    If,
+    #[token("NOT", ignore(ascii_case))]
+    Not,
+    #[token("AND", ignore(ascii_case))] //where like "ERROR" and not like "healthcheck"
+    And,
+    #[token("OR", ignore(ascii_case))]
+    Or,
+    #[token("EXISTS", ignore(ascii_case))]
+    Exists,
+    #[token("WITH", ignore(ascii_case))] //create "new_file.txt" with "line1" "line2"
+    With,
+    #[token("UPDATE", ignore(ascii_case))] //update "file.txt" set "new line" after "[section]"
+    Update,
+    #[token("SET", ignore(ascii_case))]
+    Set,
+    #[token("AT", ignore(ascii_case))]
+    At,
+    #[token("LINE", ignore(ascii_case))]
+    Line,
+    #[token("LINENO", ignore(ascii_case))] //where lineno between 100 and 200
+    LineNo,
+    #[token("BETWEEN", ignore(ascii_case))]
+    Between,
+    #[token("BEFORE", ignore(ascii_case))]
+    Before,
+    #[token("AFTER", ignore(ascii_case))]
+    After,
+    #[token("REPLACE", ignore(ascii_case))] //replace "file.txt" regex "(\d+)" "#$1"
+    Replace,
+    #[token("LIMIT", ignore(ascii_case))]
+    Limit,
+    #[token("OFFSET", ignore(ascii_case))] //select * from "file.txt" limit 10 offset 20
+    Offset,
+    #[token("SKIP", ignore(ascii_case))]
+    Skip,
+    #[token("DELETE", ignore(ascii_case))] //delete top(1) from "file.txt" where like "secret"
+    Delete,
     #[token("fn")]
     Fn,
     #[token("SELECT", ignore(ascii_case))]
@@ -44,30 +83,106 @@ pub enum Token{
     #[token("FROM", ignore(ascii_case))]
     From,
 
-
     #[regex(r"(?i)(Top)\(\d+\)", transform_top_and_tail, ignore(ascii_case))]
     Top(String),
 
     #[regex(r"(?i)(Tail)\(\d+\)", transform_top_and_tail, ignore(ascii_case))]
     Tail(String),
 
+    #[regex(
+        r"(?i)(Lines)\(\d+\.\.\d+\)",
+        transform_top_and_tail,
+        ignore(ascii_case)
+    )]
+    Lines(String),
+
+    #[token("STDIN", ignore(ascii_case))]
+    //kubectl logs my-pod | fql -e 'select * from stdin where like "panic"'
+    Stdin,
+    #[token("DIR", ignore(ascii_case))]
+    //from dir "src" recursive include "*.rs" exclude "target/**"
+    Dir,
+    #[token("RECURSIVE", ignore(ascii_case))]
+    Recursive,
+    #[token("INCLUDE", ignore(ascii_case))]
+    Include,
+    #[token("EXCLUDE", ignore(ascii_case))]
+    Exclude,
+    #[token("HIDDEN", ignore(ascii_case))]
+    Hidden,
+    #[token("FOLLOW", ignore(ascii_case))]
+    Follow,
+    #[token("SYMLINKS", ignore(ascii_case))]
+    Symlinks,
+    #[token("ENCODING", ignore(ascii_case))]
+    Encoding,
+    #[token("OUTPUT", ignore(ascii_case))]
+    Output,
+    #[token("TEXT", ignore(ascii_case))]
+    Text,
+    #[token("JSON", ignore(ascii_case))]
+    Json,
+    #[token("NDJSON", ignore(ascii_case))]
+    Ndjson,
+    #[token("CSV", ignore(ascii_case))]
+    Csv,
+    #[token("TABLE", ignore(ascii_case))]
+    Table,
+    #[token("TSV", ignore(ascii_case))]
+    //select name, amount from csv "data.csv" delimiter ";" header
+    Tsv,
+    #[token("DELIMITER", ignore(ascii_case))]
+    Delimiter,
+    #[token("HEADER", ignore(ascii_case))]
+    Header,
+    #[token("PARSE", ignore(ascii_case))]
+    //from "access.log" parse regex "(?P<ip>\S+) ..." keep unparsed
+    Parse,
+    #[token("FORMAT", ignore(ascii_case))] //from "access.log" format nginx_combined
+    Format,
+    #[token("KEEP", ignore(ascii_case))]
+    Keep,
+    #[token("UNPARSED", ignore(ascii_case))]
+    Unparsed,
 
     #[token("WHERE", ignore(ascii_case))]
     Where,
+    #[token("ORDER", ignore(ascii_case))] //order by length desc natural
+    Order,
+    #[token("BY", ignore(ascii_case))]
+    By,
+    #[token("ASC", ignore(ascii_case))]
+    Asc,
+    #[token("DESC", ignore(ascii_case))]
+    Desc,
+    #[token("NUMERIC", ignore(ascii_case))]
+    Numeric,
+    #[token("NATURAL", ignore(ascii_case))]
+    Natural,
+    #[token("LENGTH", ignore(ascii_case))]
+    Length,
     #[token(">")]
     Export,
-   
-
-
-
-    // #[regex(r"[a-zA-Z_?]+", to_string)]
-    // Identifier(String),
+    #[token(">>")]
+    ExportAppend,
+
+    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", to_string)] //a column name, keywords win over it
+    #[regex(r"`[^`]+`", to_string)] //`line`, `first name`: any column, keyword or not
+    Identifier(String),
+    //$.items[*].id
+    #[regex(
+        r#"\$(\.[a-zA-Z_][a-zA-Z0-9_]*|\[([0-9]+|\*|"[^"]*"|'[^']*')\])*"#,
+        to_string
+    )]
+    Path(String),
     #[regex(r"([0-9]+[.])?[0-9]+", to_float)]
     Number(f64),
     #[regex(r##""(?:[^"\\]|\\.)*""##, to_string)]
+    #[regex(r"'(?:[^'\\]|\\.)*'", to_string)]
     Strings(String),
-   
 
+    #[token(",")]
+    Comma,
     #[token("(")]
     LeftParen,
     #[token(")")]
@@ -77,136 +192,173 @@ pub enum Token{
     #[token("}")]
     RightBrace,
 
-
     #[token("+")]
     Plus,
     #[token("-")]
     Minus,
     #[token("/")]
     Slash,
+    #[token("%")]
+    Percent,
     #[token("=")]
     Assign,
 
-
     #[token("true")]
     True,
     #[token("false")]
     False,
-    
+
     #[token(";")]
     Eos,
     #[token("let")]
     Let,
 
-
     #[token("!=")]
     NotEquals,
+    #[token("<")]
+    Less,
+    #[token("<=")]
+    LessEquals,
+    #[token(">=")]
+    GreaterEquals,
     #[token(r"LIKE", ignore(ascii_case))]
     Like,
     #[token(r"NOTLIKE", ignore(ascii_case))]
     NotLike,
+    #[token(r"ILIKE", ignore(ascii_case))]
+    ILike,
+    #[token(r"NOTILIKE", ignore(ascii_case))]
+    NotILike,
+    #[token(r"ESCAPE", ignore(ascii_case))] //like "100\%" escape "\"
+    Escape,
     #[token("REGEX", ignore(ascii_case))]
     Regex,
-    
+
     Eof,
 
     #[error]
     #[regex(r"--[^\n]*", logos::skip)]
     #[regex(r"[ \t\n\f]+", logos::skip)]
     Error,
-
 }
 
-impl Into<String> for Token{
-    fn into(self) -> String {
-        match self{
-            //Token::Identifier(s) => s,
+impl From<Token> for String {
+    fn from(token: Token) -> String {
+        match token {
+            Token::Identifier(s) => s,
+            Token::Path(s) => s,
             Token::Strings(s) => s,
             Token::Top(s) => s,
             Token::Tail(s) => s,
-            _ => unreachable!()
+            Token::Lines(s) => s,
+            _ => unreachable!(),
         }
     }
 }
 
 #[cfg(test)]
-mod tests{
+mod tests {
     use super::*;
 
     #[test]
-    fn it_can_recognise_select_statements(){
+    fn it_can_recognise_select_statements() {
         let mut lexer = Token::lexer(r##"SELECT * FROM "C:\temp\workflow2.json";"##);
         assert_eq!(lexer.next(), Some(Token::Select));
         assert_eq!(lexer.next(), Some(Token::Star));
         assert_eq!(lexer.next(), Some(Token::From));
-        assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
         assert_eq!(lexer.next(), Some(Token::Eos));
     }
     #[test]
-    fn it_can_recognise_select_with_where_like_condition(){
-        let mut lexer = Token::lexer(r##"SELECT * FROM "C:\temp\workflow2.json" WHERE LIKE "taskid";"##);
+    fn it_can_recognise_select_with_where_like_condition() {
+        let mut lexer =
+            Token::lexer(r##"SELECT * FROM "C:\temp\workflow2.json" WHERE LIKE "taskid";"##);
         assert_eq!(lexer.next(), Some(Token::Select));
         assert_eq!(lexer.next(), Some(Token::Star));
         assert_eq!(lexer.next(), Some(Token::From));
-        assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
         assert_eq!(lexer.next(), Some(Token::Where));
         assert_eq!(lexer.next(), Some(Token::Like));
         assert_eq!(lexer.next(), Some(Token::Strings(r"taskid".to_owned())));
         assert_eq!(lexer.next(), Some(Token::Eos));
     }
     #[test]
-    fn it_can_recognise_case_insensitive(){
-        let mut lexer = Token::lexer(r##"select * fRom "C:\temp\workflow2.json" wHeRe LiKe "taskid";"##);
+    fn it_can_recognise_case_insensitive() {
+        let mut lexer =
+            Token::lexer(r##"select * fRom "C:\temp\workflow2.json" wHeRe LiKe "taskid";"##);
         assert_eq!(lexer.next(), Some(Token::Select));
         assert_eq!(lexer.next(), Some(Token::Star));
         assert_eq!(lexer.next(), Some(Token::From));
-        assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
         assert_eq!(lexer.next(), Some(Token::Where));
         assert_eq!(lexer.next(), Some(Token::Like));
         assert_eq!(lexer.next(), Some(Token::Strings(r"taskid".to_owned())));
         assert_eq!(lexer.next(), Some(Token::Eos));
     }
     #[test]
-    fn it_can_recognise_select_with_where_not_like_condition(){
-        let mut lexer = Token::lexer(r##"SELECT * FROM "C:\temp\workflow2.json" WHERE NOTLIKE "taskid";"##);
+    fn it_can_recognise_select_with_where_not_like_condition() {
+        let mut lexer =
+            Token::lexer(r##"SELECT * FROM "C:\temp\workflow2.json" WHERE NOTLIKE "taskid";"##);
         assert_eq!(lexer.next(), Some(Token::Select));
         assert_eq!(lexer.next(), Some(Token::Star));
         assert_eq!(lexer.next(), Some(Token::From));
-        assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
         assert_eq!(lexer.next(), Some(Token::Where));
         assert_eq!(lexer.next(), Some(Token::NotLike));
         assert_eq!(lexer.next(), Some(Token::Strings(r"taskid".to_owned())));
         assert_eq!(lexer.next(), Some(Token::Eos));
     }
     #[test]
-    fn it_can_recognise_select_with_count_statements(){
-        let mut lexer = Token::lexer(r##"SELECT COUNTC * FROM "C:\temp\workflow2.json"; SELECT COUNTL * FROM "C:\temp\workflow2.json"; SELECT COUNTW * FROM "C:\temp\workflow2.json"; "##);
+    fn it_can_recognise_select_with_count_statements() {
+        let mut lexer = Token::lexer(
+            r##"SELECT COUNTC * FROM "C:\temp\workflow2.json"; SELECT COUNTL * FROM "C:\temp\workflow2.json"; SELECT COUNTW * FROM "C:\temp\workflow2.json"; "##,
+        );
         //COUNTC
         assert_eq!(lexer.next(), Some(Token::Select));
         assert_eq!(lexer.next(), Some(Token::CountC));
         assert_eq!(lexer.next(), Some(Token::Star));
         assert_eq!(lexer.next(), Some(Token::From));
-        assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
         assert_eq!(lexer.next(), Some(Token::Eos));
         //countL
         assert_eq!(lexer.next(), Some(Token::Select));
         assert_eq!(lexer.next(), Some(Token::CountL));
         assert_eq!(lexer.next(), Some(Token::Star));
         assert_eq!(lexer.next(), Some(Token::From));
-        assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
+        assert_eq!(lexer.next(), Some(Token::Eos));
+        //countW
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::CountW));
+        assert_eq!(lexer.next(), Some(Token::Star));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\workflow2.json".to_owned()))
+        );
         assert_eq!(lexer.next(), Some(Token::Eos));
-         //countW
-         assert_eq!(lexer.next(), Some(Token::Select));
-         assert_eq!(lexer.next(), Some(Token::CountW));
-         assert_eq!(lexer.next(), Some(Token::Star));
-         assert_eq!(lexer.next(), Some(Token::From));
-         assert_eq!(lexer.next(), Some(Token::Strings(r"C:\temp\workflow2.json".to_owned())));
-         assert_eq!(lexer.next(), Some(Token::Eos));
     }
 
-
     #[test]
-    fn it_can_recognise_reserved_keywords(){
+    fn it_can_recognise_reserved_keywords() {
         let mut lexer = Token::lexer("fn true false SELECT FROM WHERE CREATE");
 
         assert_eq!(lexer.next(), Some(Token::Fn));
@@ -217,7 +369,328 @@ mod tests{
         assert_eq!(lexer.next(), Some(Token::From));
         assert_eq!(lexer.next(), Some(Token::Where));
         assert_eq!(lexer.next(), Some(Token::Create));
+    }
+
+    #[test]
+    fn it_can_recognise_create_statements() {
+        let mut lexer =
+            Token::lexer(r##"CREATE IF NOT EXISTS "C:\temp\new.txt" WITH "line1" "line2";"##);
+        assert_eq!(lexer.next(), Some(Token::Create));
+        assert_eq!(lexer.next(), Some(Token::If));
+        assert_eq!(lexer.next(), Some(Token::Not));
+        assert_eq!(lexer.next(), Some(Token::Exists));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r"C:\temp\new.txt".to_owned()))
+        );
+        assert_eq!(lexer.next(), Some(Token::With));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"line1".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"line2".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+    }
+
+    #[test]
+    fn it_can_recognise_update_statements() {
+        let mut lexer = Token::lexer(
+            r##"UPDATE "app.ini" SET "debug=true" AFTER "[main]"; update "app.ini" set "x" at line 3 before"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Update));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"app.ini".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Set));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"debug=true".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::After));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"[main]".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+        assert_eq!(lexer.next(), Some(Token::Update));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"app.ini".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Set));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"x".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::At));
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::Number(3.0)));
+        assert_eq!(lexer.next(), Some(Token::Before));
+    }
+
+    #[test]
+    fn it_can_recognise_replace_statements() {
+        let mut lexer = Token::lexer(
+            r##"REPLACE "app.log" REGEX "id=(\d+)" "id=$1" WHERE LIKE "user" LIMIT 5;"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Replace));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"app.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Regex));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"id=(\d+)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"id=$1".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::Like));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"user".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Limit));
+        assert_eq!(lexer.next(), Some(Token::Number(5.0)));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+    }
+
+    #[test]
+    fn it_can_recognise_delete_statements() {
+        let mut lexer = Token::lexer(
+            r##"DELETE FROM "app.log" WHERE LIKE "debug"; delete tail(2) from "app.log" where regex "^$";"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Delete));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"app.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::Like));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"debug".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+        assert_eq!(lexer.next(), Some(Token::Delete));
+        assert_eq!(lexer.next(), Some(Token::Tail("TAIL(2)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"app.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::Regex));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"^$".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+    }
+
+    #[test]
+    fn it_can_recognise_export_redirection() {
+        let mut lexer = Token::lexer(
+            r##"SELECT TOP(10) FROM "in.txt" > "out.txt"; SELECT * FROM "in.txt" >> "out.txt";"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Top("TOP(10)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"in.txt".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Export));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"out.txt".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Star));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"in.txt".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::ExportAppend));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"out.txt".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+    }
+
+    #[test]
+    fn it_can_recognise_boolean_where_conditions() {
+        let mut lexer =
+            Token::lexer(r##"WHERE (LIKE "ERROR" or like "WARN") AND NOT NOTLIKE "x""##);
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::LeftParen));
+        assert_eq!(lexer.next(), Some(Token::Like));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"ERROR".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Or));
+        assert_eq!(lexer.next(), Some(Token::Like));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"WARN".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::RightParen));
+        assert_eq!(lexer.next(), Some(Token::And));
+        assert_eq!(lexer.next(), Some(Token::Not));
+        assert_eq!(lexer.next(), Some(Token::NotLike));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"x".to_owned())));
+    }
+
+    #[test]
+    fn it_can_recognise_like_patterns() {
+        let mut lexer =
+            Token::lexer(r##"WHERE ILIKE "%error_" OR NOTILIKE "x" AND LIKE "50!%" ESCAPE "!""##);
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::ILike));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"%error_".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Or));
+        assert_eq!(lexer.next(), Some(Token::NotILike));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"x".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::And));
+        assert_eq!(lexer.next(), Some(Token::Like));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"50!%".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Escape));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"!".to_owned())));
+    }
 
+    #[test]
+    fn it_can_recognise_comparisons() {
+        let mut lexer =
+            Token::lexer(r##"LINE = "a" LINE != "b" line < 1 LINE <= 2 LINE > 3 LINE >= 4"##);
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::Assign));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"a".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::NotEquals));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"b".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::Less));
+        assert_eq!(lexer.next(), Some(Token::Number(1.0)));
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::LessEquals));
+        assert_eq!(lexer.next(), Some(Token::Number(2.0)));
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::Export));
+        assert_eq!(lexer.next(), Some(Token::Number(3.0)));
+        assert_eq!(lexer.next(), Some(Token::Line));
+        assert_eq!(lexer.next(), Some(Token::GreaterEquals));
+        assert_eq!(lexer.next(), Some(Token::Number(4.0)));
+    }
+
+    #[test]
+    fn it_can_recognise_line_numbers_and_ranges() {
+        let mut lexer = Token::lexer(
+            r##"SELECT lines(100..200) FROM "a.log" WHERE LINENO BETWEEN 1 AND 5 OR LINENO % 10 = 0 WITH LINENO"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Lines("LINES(100..200)".to_owned()))
+        );
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"a.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::LineNo));
+        assert_eq!(lexer.next(), Some(Token::Between));
+        assert_eq!(lexer.next(), Some(Token::Number(1.0)));
+        assert_eq!(lexer.next(), Some(Token::And));
+        assert_eq!(lexer.next(), Some(Token::Number(5.0)));
+        assert_eq!(lexer.next(), Some(Token::Or));
+        assert_eq!(lexer.next(), Some(Token::LineNo));
+        assert_eq!(lexer.next(), Some(Token::Percent));
+        assert_eq!(lexer.next(), Some(Token::Number(10.0)));
+        assert_eq!(lexer.next(), Some(Token::Assign));
+        assert_eq!(lexer.next(), Some(Token::Number(0.0)));
+        assert_eq!(lexer.next(), Some(Token::With));
+        assert_eq!(lexer.next(), Some(Token::LineNo));
+    }
+
+    #[test]
+    fn it_can_recognise_pagination() {
+        let mut lexer = Token::lexer(r##"LIMIT 10 OFFSET 20 skip 5"##);
+        assert_eq!(lexer.next(), Some(Token::Limit));
+        assert_eq!(lexer.next(), Some(Token::Number(10.0)));
+        assert_eq!(lexer.next(), Some(Token::Offset));
+        assert_eq!(lexer.next(), Some(Token::Number(20.0)));
+        assert_eq!(lexer.next(), Some(Token::Skip));
+        assert_eq!(lexer.next(), Some(Token::Number(5.0)));
+    }
+
+    #[test]
+    fn it_can_recognise_dir_sources() {
+        let mut lexer = Token::lexer(
+            r##"FROM DIR "src" RECURSIVE INCLUDE "*.rs" EXCLUDE "target/**" hidden follow symlinks"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Dir));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"src".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Recursive));
+        assert_eq!(lexer.next(), Some(Token::Include));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"*.rs".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Exclude));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"target/**".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Hidden));
+        assert_eq!(lexer.next(), Some(Token::Follow));
+        assert_eq!(lexer.next(), Some(Token::Symlinks));
+    }
+
+    #[test]
+    fn it_can_recognise_stdin() {
+        let mut lexer = Token::lexer(r##"SELECT * FROM STDIN; select * from "-";"##);
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Star));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Stdin));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Star));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"-".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
+    }
+
+    #[test]
+    fn it_can_recognise_output_formats() {
+        let mut lexer = Token::lexer(r##"OUTPUT json output NDJSON Csv TABLE text"##);
+        assert_eq!(lexer.next(), Some(Token::Output));
+        assert_eq!(lexer.next(), Some(Token::Json));
+        assert_eq!(lexer.next(), Some(Token::Output));
+        assert_eq!(lexer.next(), Some(Token::Ndjson));
+        assert_eq!(lexer.next(), Some(Token::Csv));
+        assert_eq!(lexer.next(), Some(Token::Table));
+        assert_eq!(lexer.next(), Some(Token::Text));
+    }
+
+    #[test]
+    fn it_can_recognise_columns() {
+        let mut lexer = Token::lexer(
+            r##"SELECT name, total_2 FROM CSV 'data.csv' DELIMITER ';' HEADER WHERE line > 1"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Identifier("name".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Comma));
+        assert_eq!(lexer.next(), Some(Token::Identifier("total_2".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Csv));
+        assert_eq!(lexer.next(), Some(Token::Strings("data.csv".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Delimiter));
+        assert_eq!(lexer.next(), Some(Token::Strings(";".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Header));
+        assert_eq!(lexer.next(), Some(Token::Where));
+        //a keyword is never a column
+        assert_eq!(lexer.next(), Some(Token::Line));
+
+        //unless it is quoted
+        let mut lexer = Token::lexer(r##"SELECT `line`, `first name` ORDER BY `length`"##);
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Identifier("line".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Comma));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Identifier("first name".to_owned()))
+        );
+        assert_eq!(lexer.next(), Some(Token::Order));
+        assert_eq!(lexer.next(), Some(Token::By));
+        assert_eq!(lexer.next(), Some(Token::Identifier("length".to_owned())));
+    }
+
+    #[test]
+    fn it_can_recognise_order_by() {
+        let mut lexer =
+            Token::lexer(r##"SELECT TOP(10) * FROM "a.log" ORDER BY length DESC natural"##);
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Top("TOP(10)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Star));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings("a.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Order));
+        assert_eq!(lexer.next(), Some(Token::By));
+        assert_eq!(lexer.next(), Some(Token::Length));
+        assert_eq!(lexer.next(), Some(Token::Desc));
+        assert_eq!(lexer.next(), Some(Token::Natural));
+    }
+
+    #[test]
+    fn it_can_recognise_json_paths() {
+        let mut lexer = Token::lexer(
+            r##"SELECT $.items[*].id, $["a key"] FROM NDJSON "events.log" WHERE $.level = 'error'"##,
+        );
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Path("$.items[*].id".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Comma));
+        assert_eq!(lexer.next(), Some(Token::Path(r#"$["a key"]"#.to_owned())));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Ndjson));
+        assert_eq!(lexer.next(), Some(Token::Strings("events.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Where));
+        assert_eq!(lexer.next(), Some(Token::Path("$.level".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Assign));
+    }
+
+    #[test]
+    fn it_can_recognise_encodings() {
+        let mut lexer = Token::lexer(r##"SELECT * FROM "old.log" encoding "latin1";"##);
+        assert_eq!(lexer.next(), Some(Token::Select));
+        assert_eq!(lexer.next(), Some(Token::Star));
+        assert_eq!(lexer.next(), Some(Token::From));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"old.log".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Encoding));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"latin1".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Eos));
     }
 
     // #[test]
@@ -232,30 +705,34 @@ mod tests{
 
     //     assert_eq!(lexer.next(), Some(Token::Identifier("salary".to_owned())));
 
-
-
     // }
 
     #[test]
-    fn it_can_recognise_numbers(){
+    fn it_can_recognise_numbers() {
         let mut lexer = Token::lexer("12345 6789.98 2.5 4.51");
         assert_eq!(lexer.next(), Some(Token::Number(12345.0)));
         assert_eq!(lexer.next(), Some(Token::Number(6789.98)));
         assert_eq!(lexer.next(), Some(Token::Number(2.5)));
         assert_eq!(lexer.next(), Some(Token::Number(4.51)));
     }
-    
+
     #[test]
-    fn it_can_recognise_strings(){
+    fn it_can_recognise_strings() {
         let mut lexer = Token::lexer(r##""testing" "testing with \"" "testing \n""##);
-        assert_eq!(lexer.next(), Some(Token::Strings((r"testing".to_owned()))));
-
-        assert_eq!(lexer.next(), Some(Token::Strings((r#"testing with \""#.to_owned()))));
-        assert_eq!(lexer.next(), Some(Token::Strings((r#"testing \n"#.to_owned()))));
+        assert_eq!(lexer.next(), Some(Token::Strings(r"testing".to_owned())));
+
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r#"testing with \""#.to_owned()))
+        );
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Strings(r#"testing \n"#.to_owned()))
+        );
     }
 
     #[test]
-    fn it_can_recognise_symbols(){
+    fn it_can_recognise_symbols() {
         let mut lexer = Token::lexer("( ) { } + - * /");
         assert_eq!(lexer.next(), Some(Token::LeftParen));
         assert_eq!(lexer.next(), Some(Token::RightParen));
@@ -265,25 +742,21 @@ mod tests{
         assert_eq!(lexer.next(), Some(Token::Plus));
         assert_eq!(lexer.next(), Some(Token::Minus));
         assert_eq!(lexer.next(), Some(Token::Star));
-        assert_eq!(lexer.next(), Some(Token::Slash));  
-
+        assert_eq!(lexer.next(), Some(Token::Slash));
     }
 
- #[test]
- fn it_can_match_top_and_tail_lexis(){
-    let mut lexer = Token::lexer(r##"Top(15) Top(100) Tail(15) Tail(100) tOp(12)"##);
-
-    assert_eq!(lexer.next(), Some(Token::Top("TOP(15)".to_owned())));
-    assert_eq!(lexer.next(), Some(Token::Top("TOP(100)".to_owned())));
-
-    assert_eq!(lexer.next(), Some(Token::Tail("TAIL(15)".to_owned())));
-    assert_eq!(lexer.next(), Some(Token::Tail("TAIL(100)".to_owned())));
-
-    assert_eq!(lexer.next(), Some(Token::Top("TOP(12)".to_owned())));
-
- }
-
+    #[test]
+    fn it_can_match_top_and_tail_lexis() {
+        let mut lexer =
+            Token::lexer(r##"Top(15) Top(100) Tail(15) Tail(100) tOp(12) Lines(3..7)"##);
 
+        assert_eq!(lexer.next(), Some(Token::Top("TOP(15)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Top("TOP(100)".to_owned())));
 
+        assert_eq!(lexer.next(), Some(Token::Tail("TAIL(15)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Tail("TAIL(100)".to_owned())));
 
-}
\ No newline at end of file
+        assert_eq!(lexer.next(), Some(Token::Top("TOP(12)".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Lines("LINES(3..7)".to_owned())));
+    }
+}